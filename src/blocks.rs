@@ -0,0 +1,253 @@
+use serde_json::{Value, json};
+
+use crate::emoji::EmojiMaps;
+use crate::error::Result;
+use crate::locale::Locale;
+use crate::models::{AdvisoryChanges, Forecast, TempDiff, TextOptions};
+use crate::slack::Message;
+
+/// The time slots of `RainProbability`.
+const RAIN_SLOTS: [&str; 4] = ["00-06", "06-12", "12-18", "18-24"];
+
+/// A card of the forecast: the weather, the temperatures, the advisories and the 降水確率 of the day.
+/// `text` is the status text.
+pub fn forecast(forecast: &Forecast, url: &str, options: &TextOptions) -> Result<Message> {
+    let locale = options.locale;
+    let unit = options.unit;
+    let temp = |label: &str, temp, diff: &TempDiff| {
+        format!(
+            "{}: {}{} [{}]",
+            label,
+            unit.convert(temp),
+            unit.symbol(),
+            diff.convert(unit)
+        )
+    };
+
+    let mut blocks = vec![
+        header(&forecast.place),
+        section(&format!(
+            "{} *{}*\n{}  {}",
+            forecast.build_weather_emoji(&options.emoji_maps),
+            locale.telop(&forecast.weather),
+            temp(locale.high(), forecast.high_temp, &forecast.high_temp_diff),
+            temp(locale.low(), forecast.low_temp, &forecast.low_temp_diff)
+        )),
+    ];
+
+    if let Some(first) = forecast.advisories.first() {
+        let emoji = alert_emoji(first.name(), &options.emoji_maps);
+        let names = forecast
+            .advisories
+            .iter()
+            .map(|x| locale.advisory(x))
+            .collect::<Vec<_>>()
+            .join(locale.list_separator());
+        blocks.push(section(&format!("{emoji} {names}")));
+    }
+
+    let slots = RAIN_SLOTS
+        .iter()
+        .zip(forecast.rain_probability.slots())
+        .map(|(slot, x)| match x {
+            Some(x) => format!("{slot} {x}%"),
+            None => format!("{slot} ---"),
+        })
+        .collect::<Vec<_>>();
+    blocks.push(section(&format!(
+        ":umbrella: {}: {}",
+        locale.rain(),
+        slots.join("  ")
+    )));
+    blocks.push(source(forecast, url, locale));
+
+    Ok(Message {
+        text: forecast.build_text_with(options, None)?,
+        blocks,
+    })
+}
+
+/// A message of the advisories which were issued, lifted or changed their level, e.g. 練馬区: 大雨警報 発表.
+pub fn advisory_changes(
+    forecast: &Forecast,
+    url: &str,
+    changes: &AdvisoryChanges,
+    locale: Locale,
+    emoji_maps: &EmojiMaps,
+) -> Message {
+    let issued = changes.issued.iter().map(|x| {
+        let emoji = alert_emoji(x.name(), emoji_maps);
+        let name = locale.advisory(x);
+        (
+            format!("{name} {}", locale.issued()),
+            format!("{emoji} *{name}* {}", locale.issued()),
+        )
+    });
+    let lifted = changes.lifted.iter().map(|x| {
+        let name = locale.advisory(x);
+        (
+            format!("{name} {}", locale.lifted()),
+            format!("~{name}~ {}", locale.lifted()),
+        )
+    });
+    let changed = changes.upgraded.iter().chain(&changes.downgraded).map(|x| {
+        let emoji = alert_emoji(x.to.name(), emoji_maps);
+        let (from, to) = (locale.advisory(&x.from), locale.advisory(&x.to));
+        (
+            format!("{from} → {to}"),
+            format!("{emoji} ~{from}~ → *{to}*"),
+        )
+    });
+    let (texts, lines): (Vec<_>, Vec<_>) = issued.chain(changed).chain(lifted).unzip();
+
+    let mut blocks = vec![header(&forecast.place)];
+    blocks.extend(lines.iter().map(|x| section(x)));
+    blocks.push(source(forecast, url, locale));
+
+    Message {
+        text: format!(
+            "{}: {}",
+            forecast.place,
+            texts.join(locale.list_separator())
+        ),
+        blocks,
+    }
+}
+
+fn alert_emoji<'a>(name: &str, emoji_maps: &'a EmojiMaps) -> &'a str {
+    emoji_maps
+        .alert(name)
+        .unwrap_or(emoji_maps.alert_fallback())
+}
+
+/// A link to the page and its 発表.
+fn source(forecast: &Forecast, url: &str, locale: Locale) -> Value {
+    context(&format!(
+        "<{url}|tenki.jp> {}",
+        locale.date_time(&forecast.date_time)
+    ))
+}
+
+fn header(text: &str) -> Value {
+    json!({ "type": "header", "text": { "type": "plain_text", "text": text } })
+}
+
+fn section(mrkdwn: &str) -> Value {
+    json!({ "type": "section", "text": { "type": "mrkdwn", "text": mrkdwn } })
+}
+
+fn context(mrkdwn: &str) -> Value {
+    json!({ "type": "context", "elements": [{ "type": "mrkdwn", "text": mrkdwn }] })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Advisory, LevelChange, RainProbability, Severity, TempDiff};
+    use pretty_assertions::assert_eq;
+
+    fn forecast() -> Forecast {
+        Forecast {
+            place: "練馬区".to_string(),
+            date_time: "30日16:00".to_string(),
+            advisories: vec![Advisory::new("大雨", Severity::Warning)],
+            weather: "雨".to_string(),
+            weather_icon_name: "15".to_string(),
+            high_temp: 29,
+            high_temp_diff: TempDiff::new("-4").unwrap(),
+            low_temp: 26,
+            low_temp_diff: TempDiff::new("+1").unwrap(),
+            rain_probability: RainProbability::default(),
+            wind: None,
+            wave: None,
+        }
+    }
+
+    #[test]
+    fn test_forecast() {
+        let forecast = Forecast {
+            rain_probability: RainProbability::new([None, Some(10), Some(60), Some(30)]),
+            ..forecast()
+        };
+        let message = super::forecast(
+            &forecast,
+            "https://tenki.jp/forecast/3/16/4410/13120",
+            &TextOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            message.text,
+            "練馬区: 大雨警報 :umbrella:: 雨 最高: 29℃[-4] 最低: 26℃[+1] 発表: 30日16:00"
+        );
+        assert_eq!(
+            json!(message.blocks),
+            json!([
+                { "type": "header", "text": { "type": "plain_text", "text": "練馬区" } },
+                {
+                    "type": "section",
+                    "text": { "type": "mrkdwn", "text": ":umbrella: *雨*\n最高: 29℃ [-4]  最低: 26℃ [+1]" }
+                },
+                { "type": "section", "text": { "type": "mrkdwn", "text": ":bucket: 大雨警報" } },
+                {
+                    "type": "section",
+                    "text": {
+                        "type": "mrkdwn",
+                        "text": ":umbrella: 降水確率: 00-06 ---  06-12 10%  12-18 60%  18-24 30%"
+                    }
+                },
+                {
+                    "type": "context",
+                    "elements": [{
+                        "type": "mrkdwn",
+                        "text": "<https://tenki.jp/forecast/3/16/4410/13120|tenki.jp> 30日16:00"
+                    }]
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_advisory_changes() {
+        let changes = AdvisoryChanges {
+            issued: vec![Advisory::new("大雨", Severity::Warning)],
+            lifted: vec![Advisory::new("洪水", Severity::Warning)],
+            upgraded: vec![LevelChange {
+                from: Advisory::new("暴風", Severity::Warning),
+                to: Advisory::new("暴風", Severity::SpecialWarning),
+            }],
+            downgraded: vec![],
+        };
+        let message = advisory_changes(
+            &forecast(),
+            "https://tenki.jp/forecast/3/16/4410/13120",
+            &changes,
+            Locale::Ja,
+            &EmojiMaps::default(),
+        );
+
+        assert_eq!(
+            message.text,
+            "練馬区: 大雨警報 発表,暴風警報 → 暴風特別警報,洪水警報 解除"
+        );
+        assert_eq!(
+            json!(message.blocks),
+            json!([
+                { "type": "header", "text": { "type": "plain_text", "text": "練馬区" } },
+                { "type": "section", "text": { "type": "mrkdwn", "text": ":bucket: *大雨警報* 発表" } },
+                {
+                    "type": "section",
+                    "text": { "type": "mrkdwn", "text": ":cyclone: ~暴風警報~ → *暴風特別警報*" }
+                },
+                { "type": "section", "text": { "type": "mrkdwn", "text": "~洪水警報~ 解除" } },
+                {
+                    "type": "context",
+                    "elements": [{
+                        "type": "mrkdwn",
+                        "text": "<https://tenki.jp/forecast/3/16/4410/13120|tenki.jp> 30日16:00"
+                    }]
+                },
+            ])
+        );
+    }
+}
@@ -0,0 +1,811 @@
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::emoji::EmojiMaps;
+use crate::error::{Error, Result};
+use crate::locale::Locale;
+use crate::models::{AlertKind, AlertOnly, Day, Ranking, Severity, TempUnit};
+use crate::retry::RetryPolicy;
+use crate::schedule::{MAX_INTERVAL, Schedule};
+use crate::slack::{Destination, STATUS_TEXT_MAX_LENGTH};
+use crate::state::{DEFAULT_KEY, State};
+use crate::template::Template;
+
+const CONFIG_FILE: &str = "rusty-slack-weather-status/config.toml";
+const SLACK_TOKEN_ENV: &str = "SLACK_TOKEN";
+const TENKI_JP_URL_ENV: &str = "TENKI_JP_URL";
+const DEFAULT_EVENING_HOUR: u32 = 18;
+const DEFAULT_POST_HOUR: u32 = 7;
+const DEFAULT_EXPIRE_AFTER_MINUTES: u64 = 120;
+const DEFAULT_INTERVAL_MINUTES: u64 = 10;
+const DEFAULT_JITTER_SECS: u64 = 30;
+const MAX_MINUTES: u64 = MAX_INTERVAL.as_secs() / 60;
+
+/// Settings from a config file, environment variables and CLI options. Every field is optional
+/// so that they can be merged, and the getters fill in the defaults.
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub url: Option<String>,
+    pub token: Option<String>,
+    pub day: Option<String>, // today, tomorrow or auto
+    pub evening_hour: Option<u32>,
+    pub rain: Option<bool>,
+    pub template: Option<String>,
+    pub max_length: Option<usize>, // characters of the status text
+    pub lang: Option<String>,      // ja or en
+    pub unit: Option<String>,      // celsius or fahrenheit
+    pub emoji_file: Option<PathBuf>,
+    pub priority: Option<Vec<String>>, // phenomena which come first, e.g. 大雨
+    pub mute: Option<Vec<String>>,     // phenomena which are ignored, e.g. 乾燥
+    pub alert_only: Option<String>,    // alert, warning or special_warning
+    pub alert_only_action: Option<String>, // clear or keep
+    pub notify_channel: Option<String>, // posts with the top-level token
+    pub notify_webhook: Option<String>, // an incoming webhook URL
+    pub notify_level: Option<String>,  // alert, warning or special_warning
+    pub post_to: Option<String>,       // a channel of the forecast card
+    pub post_only: Option<bool>,       // doesn't set the status
+    pub post_hour: Option<u32>,        // JST. The card is posted once a day from the hour.
+    pub expire_after: Option<u64>,     // minutes. 0 means never.
+    pub keep_manual_status: Option<bool>,
+    pub state_file: Option<PathBuf>,
+    pub retries: Option<u32>,
+    pub retry_backoff: Option<u64>, // seconds
+    pub timeout: Option<u64>,       // seconds
+    pub interval: Option<u64>,      // minutes of the daemon mode
+    pub jitter: Option<u64>,        // seconds
+    pub profiles: Vec<ProfileConfig>,
+}
+
+/// A user whose status is set. `url`, `token` and `template` default to the top-level ones.
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProfileConfig {
+    pub name: String,
+    pub url: Option<String>,
+    pub token: Option<String>,
+    pub template: Option<String>,
+}
+
+/// A profile whose defaults are filled in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct User {
+    pub name: String,
+    pub url: String,
+    pub token: Option<String>, // not needed for a dry run
+    pub template: Template,
+}
+
+/// `$XDG_*` or `~/<home_dir>`, joined with `file`.
+pub(crate) fn xdg_path(var: &str, home_dir: &str, file: &str) -> Option<PathBuf> {
+    env::var_os(var)
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|x| Path::new(&x).join(home_dir)))
+        .map(|x| x.join(file))
+}
+
+impl User {
+    pub fn token(&self) -> Result<&str> {
+        self.token.as_deref().ok_or_else(|| {
+            Error::InvalidConfig(format!(
+                "token of {} is required (--token or {SLACK_TOKEN_ENV})",
+                self.name
+            ))
+        })
+    }
+}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/rusty-slack-weather-status/config.toml` or `~/.config/rusty-slack-weather-status/config.toml`
+    pub fn default_path() -> Option<PathBuf> {
+        xdg_path("XDG_CONFIG_HOME", ".config", CONFIG_FILE)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let raw = fs::read_to_string(path)?;
+        Self::from_toml(path, &raw)
+    }
+
+    /// Same as `load`, but returns an empty config when the file doesn't exist.
+    pub fn load_if_exists(path: &Path) -> Result<Self> {
+        match path.exists() {
+            true => Self::load(path),
+            false => Ok(Self::default()),
+        }
+    }
+
+    fn from_toml(path: &Path, raw: &str) -> Result<Self> {
+        toml::from_str(raw).map_err(|e| Error::InvalidFile(path.to_path_buf(), e.to_string()))
+    }
+
+    /// `SLACK_TOKEN` and `TENKI_JP_URL`
+    pub fn from_env() -> Self {
+        Self {
+            url: env::var(TENKI_JP_URL_ENV).ok(),
+            token: env::var(SLACK_TOKEN_ENV).ok(),
+            ..Self::default()
+        }
+    }
+
+    /// Fields of `other` take precedence.
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            url: other.url.or(self.url),
+            token: other.token.or(self.token),
+            day: other.day.or(self.day),
+            evening_hour: other.evening_hour.or(self.evening_hour),
+            rain: other.rain.or(self.rain),
+            template: other.template.or(self.template),
+            max_length: other.max_length.or(self.max_length),
+            lang: other.lang.or(self.lang),
+            unit: other.unit.or(self.unit),
+            emoji_file: other.emoji_file.or(self.emoji_file),
+            priority: other.priority.or(self.priority),
+            mute: other.mute.or(self.mute),
+            alert_only: other.alert_only.or(self.alert_only),
+            alert_only_action: other.alert_only_action.or(self.alert_only_action),
+            notify_channel: other.notify_channel.or(self.notify_channel),
+            notify_webhook: other.notify_webhook.or(self.notify_webhook),
+            notify_level: other.notify_level.or(self.notify_level),
+            post_to: other.post_to.or(self.post_to),
+            post_only: other.post_only.or(self.post_only),
+            post_hour: other.post_hour.or(self.post_hour),
+            expire_after: other.expire_after.or(self.expire_after),
+            keep_manual_status: other.keep_manual_status.or(self.keep_manual_status),
+            state_file: other.state_file.or(self.state_file),
+            retries: other.retries.or(self.retries),
+            retry_backoff: other.retry_backoff.or(self.retry_backoff),
+            timeout: other.timeout.or(self.timeout),
+            interval: other.interval.or(self.interval),
+            jitter: other.jitter.or(self.jitter),
+            profiles: match other.profiles.is_empty() {
+                true => self.profiles,
+                false => other.profiles,
+            },
+        }
+    }
+
+    /// Checks every option, so that an invalid one fails at the start rather than in a run.
+    pub fn validate(&self) -> Result<()> {
+        self.users()?;
+        self.day()?;
+        self.template()?;
+        self.locale()?;
+        self.unit()?;
+        self.emoji_maps()?;
+        self.ranking()?;
+        self.alert_only()?;
+        if self.destination()?.is_some() && self.state_path().is_none() {
+            return Err(Error::InvalidConfig(
+                "state_file is required for notify_channel and notify_webhook".to_string(),
+            ));
+        }
+        self.notify_level()?;
+        if self.post_to()?.is_some() && self.state_path().is_none() {
+            return Err(Error::InvalidConfig(
+                "state_file is required for post_to".to_string(),
+            ));
+        }
+        self.post_only()?;
+        self.post_hour()?;
+        self.expire_after()?;
+        self.schedule()?;
+        Ok(())
+    }
+
+    /// The profiles, or a single user named "default" from the top-level settings.
+    pub fn users(&self) -> Result<Vec<User>> {
+        if self.profiles.is_empty() {
+            return Ok(vec![User {
+                name: DEFAULT_KEY.to_string(),
+                url: self.url()?.to_string(),
+                token: self.token.clone(),
+                template: self.template()?,
+            }]);
+        }
+
+        // The last statuses are saved per name, so a duplicate would mix them up.
+        let mut names = HashSet::new();
+        self.profiles
+            .iter()
+            .map(|profile| {
+                if profile.name.is_empty() {
+                    return Err(Error::InvalidConfig("profiles need a name".to_string()));
+                }
+                if !names.insert(profile.name.as_str()) {
+                    return Err(Error::InvalidConfig(format!(
+                        "duplicate profile name: {}",
+                        profile.name
+                    )));
+                }
+
+                Ok(User {
+                    name: profile.name.clone(),
+                    url: match &profile.url {
+                        Some(url) => url.clone(),
+                        None => self.url()?.to_string(),
+                    },
+                    token: profile.token.clone().or_else(|| self.token.clone()),
+                    template: match &profile.template {
+                        Some(template) => template.parse()?,
+                        None => self.template()?,
+                    },
+                })
+            })
+            .collect()
+    }
+
+    pub fn url(&self) -> Result<&str> {
+        self.url.as_deref().ok_or_else(|| {
+            Error::InvalidConfig(format!("url is required (--url or {TENKI_JP_URL_ENV})"))
+        })
+    }
+
+    pub fn day(&self) -> Result<Day> {
+        match self.day.as_deref() {
+            None => Ok(Day::Today),
+            Some("auto") => match self.evening_hour.unwrap_or(DEFAULT_EVENING_HOUR) {
+                x @ 0..=23 => Ok(Day::Auto { evening_hour: x }),
+                x => Err(Error::InvalidConfig(format!(
+                    "evening_hour must be 0 to 23: {x}"
+                ))),
+            },
+            Some(x) => x.parse().map_err(Error::InvalidConfig),
+        }
+    }
+
+    pub fn rain(&self) -> bool {
+        self.rain.unwrap_or_default()
+    }
+
+    /// The default format of the locale unless it's set.
+    pub fn template(&self) -> Result<Template> {
+        match &self.template {
+            Some(template) => template.parse(),
+            None => self.locale()?.default_template(),
+        }
+    }
+
+    pub fn locale(&self) -> Result<Locale> {
+        match self.lang.as_deref() {
+            Some(x) => x.parse().map_err(Error::InvalidConfig),
+            None => Ok(Locale::default()),
+        }
+    }
+
+    pub fn max_length(&self) -> usize {
+        self.max_length.unwrap_or(STATUS_TEXT_MAX_LENGTH)
+    }
+
+    pub fn unit(&self) -> Result<TempUnit> {
+        match self.unit.as_deref() {
+            Some(x) => x.parse().map_err(Error::InvalidConfig),
+            None => Ok(TempUnit::default()),
+        }
+    }
+
+    /// The embedded maps, with the override file merged over them.
+    pub fn emoji_maps(&self) -> Result<EmojiMaps> {
+        match &self.emoji_file {
+            Some(path) => EmojiMaps::load(path),
+            None => Ok(EmojiMaps::default()),
+        }
+    }
+
+    /// A phenomenon which isn't known to JMA is an error, since it's most likely a typo.
+    pub fn ranking(&self) -> Result<Ranking> {
+        let kinds = |names: &Option<Vec<String>>, option: &str| {
+            names
+                .iter()
+                .flatten()
+                .map(|x| match AlertKind::from(x.as_str()) {
+                    AlertKind::Other(x) => Err(Error::InvalidConfig(format!(
+                        "unknown phenomenon of {option}: {x}"
+                    ))),
+                    kind => Ok(kind),
+                })
+                .collect::<Result<Vec<_>>>()
+        };
+        Ok(Ranking {
+            priority: kinds(&self.priority, "priority")?,
+            mute: kinds(&self.mute, "mute")?,
+        })
+    }
+
+    /// `None` unless `alert_only` is set.
+    pub fn alert_only(&self) -> Result<Option<AlertOnly>> {
+        let Some(level) = &self.alert_only else {
+            return Ok(None);
+        };
+
+        Ok(Some(AlertOnly {
+            min_level: level.parse().map_err(Error::InvalidConfig)?,
+            action: match &self.alert_only_action {
+                Some(x) => x.parse().map_err(Error::InvalidConfig)?,
+                None => Default::default(),
+            },
+        }))
+    }
+
+    /// Where the issued and lifted advisories are posted. `None` unless a channel or a webhook is set.
+    pub fn destination(&self) -> Result<Option<Destination>> {
+        match (&self.notify_channel, &self.notify_webhook) {
+            (Some(_), Some(_)) => Err(Error::InvalidConfig(
+                "notify_channel and notify_webhook can't be set together".to_string(),
+            )),
+            (Some(channel), None) => self.channel(channel, "notify_channel").map(Some),
+            (None, Some(url)) => Ok(Some(Destination::Webhook(url.clone()))),
+            (None, None) => Ok(None),
+        }
+    }
+
+    /// The channel which the forecast card is posted to.
+    pub fn post_to(&self) -> Result<Option<Destination>> {
+        self.post_to
+            .as_ref()
+            .map(|x| self.channel(x, "post_to"))
+            .transpose()
+    }
+
+    /// Whether only the forecast card is posted, without setting the status.
+    pub fn post_only(&self) -> Result<bool> {
+        match (self.post_only.unwrap_or_default(), &self.post_to) {
+            (true, None) => Err(Error::InvalidConfig("post_only needs post_to".to_string())),
+            (x, _) => Ok(x),
+        }
+    }
+
+    /// The hour in JST from which the forecast card of the day is posted.
+    pub fn post_hour(&self) -> Result<u32> {
+        match self.post_hour.unwrap_or(DEFAULT_POST_HOUR) {
+            x @ 0..=23 => Ok(x),
+            x => Err(Error::InvalidConfig(format!(
+                "post_hour must be 0 to 23: {x}"
+            ))),
+        }
+    }
+
+    /// A channel which is posted to with the top-level token.
+    fn channel(&self, channel: &str, option: &str) -> Result<Destination> {
+        Ok(Destination::Channel {
+            token: self.token.clone().ok_or_else(|| {
+                Error::InvalidConfig(format!(
+                    "token is required for {option} (--token or {SLACK_TOKEN_ENV})"
+                ))
+            })?,
+            channel: channel.to_string(),
+        })
+    }
+
+    /// The advisories of this level or more severe are posted. Default: warning
+    pub fn notify_level(&self) -> Result<Severity> {
+        match self.notify_level.as_deref() {
+            Some(x) => x.parse().map_err(Error::InvalidConfig),
+            None => Ok(Severity::Warning),
+        }
+    }
+
+    pub fn expire_after(&self) -> Result<Option<Duration>> {
+        match self.expire_after.unwrap_or(DEFAULT_EXPIRE_AFTER_MINUTES) {
+            0 => Ok(None),
+            x => minutes(x, "expire_after").map(Some),
+        }
+    }
+
+    pub fn keep_manual_status(&self) -> bool {
+        self.keep_manual_status.unwrap_or_default()
+    }
+
+    pub fn state_path(&self) -> Option<PathBuf> {
+        self.state_file.clone().or_else(State::default_path)
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        let default = RetryPolicy::default();
+        RetryPolicy {
+            max_retries: self.retries.unwrap_or(default.max_retries),
+            initial_backoff: self
+                .retry_backoff
+                .map(Duration::from_secs)
+                .unwrap_or(default.initial_backoff),
+            timeout: self
+                .timeout
+                .map(Duration::from_secs)
+                .unwrap_or(default.timeout),
+        }
+    }
+
+    pub fn schedule(&self) -> Result<Schedule> {
+        let interval = match self.interval.unwrap_or(DEFAULT_INTERVAL_MINUTES) {
+            0 => return Err(Error::InvalidConfig("interval must not be 0".to_string())),
+            x => minutes(x, "interval")?,
+        };
+        let jitter = Duration::from_secs(self.jitter.unwrap_or(DEFAULT_JITTER_SECS));
+        if jitter > MAX_INTERVAL {
+            return Err(Error::InvalidConfig(format!(
+                "jitter must be at most {} seconds: {}",
+                MAX_INTERVAL.as_secs(),
+                jitter.as_secs()
+            )));
+        }
+
+        Ok(Schedule { interval, jitter })
+    }
+}
+
+/// Minutes of an option, which are at most `MAX_INTERVAL` so that adding them to a time doesn't overflow.
+fn minutes(minutes: u64, option: &str) -> Result<Duration> {
+    match minutes.checked_mul(60) {
+        Some(x) if minutes <= MAX_MINUTES => Ok(Duration::from_secs(x)),
+        _ => Err(Error::InvalidConfig(format!(
+            "{option} must be at most {MAX_MINUTES} minutes: {minutes}"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{NoAlertAction, Severity};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_from_toml() {
+        let config = Config::from_toml(
+            Path::new("config.toml"),
+            r#"
+url = "https://tenki.jp/forecast/3/16/4410/13113"
+token = "xoxp-token"
+day = "auto"
+evening_hour = 17
+priority = ["雷"]
+mute = ["乾燥", "濃霧"]
+alert_only = "warning"
+alert_only_action = "keep"
+expire_after = 0
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.url().unwrap(),
+            "https://tenki.jp/forecast/3/16/4410/13113"
+        );
+        assert_eq!(config.token.as_deref(), Some("xoxp-token"));
+        assert_eq!(config.day().unwrap(), Day::Auto { evening_hour: 17 });
+        assert_eq!(
+            config.ranking().unwrap(),
+            Ranking {
+                priority: vec![AlertKind::Thunderstorm],
+                mute: vec![AlertKind::DryAir, AlertKind::DenseFog],
+            }
+        );
+        assert_eq!(
+            config.alert_only().unwrap(),
+            Some(AlertOnly {
+                min_level: Severity::Warning,
+                action: NoAlertAction::Keep,
+            })
+        );
+        assert_eq!(config.expire_after().unwrap(), None);
+    }
+
+    #[test]
+    fn test_invalid_evening_hour() {
+        let config = Config {
+            day: Some("auto".to_string()),
+            evening_hour: Some(24),
+            ..Config::default()
+        };
+
+        assert!(matches!(config.day(), Err(Error::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn test_unknown_phenomenon() {
+        let config = Config {
+            priority: Some(vec!["雷".to_string()]),
+            mute: Some(vec!["乾操".to_string()]),
+            ..Config::default()
+        };
+
+        assert!(matches!(
+            config.ranking(),
+            Err(Error::InvalidConfig(x)) if x == "unknown phenomenon of mute: 乾操"
+        ));
+    }
+
+    #[test]
+    fn test_invalid_minutes() {
+        let config = |interval, expire_after| Config {
+            interval: Some(interval),
+            expire_after: Some(expire_after),
+            ..Config::default()
+        };
+
+        assert!(matches!(
+            config(0, 0).schedule(),
+            Err(Error::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            config(u64::MAX, 0).schedule(),
+            Err(Error::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            config(10, u64::MAX / 60 + 1).expire_after(),
+            Err(Error::InvalidConfig(_))
+        ));
+        assert_eq!(
+            config(MAX_MINUTES, MAX_MINUTES)
+                .schedule()
+                .unwrap()
+                .interval,
+            MAX_INTERVAL
+        );
+        assert!(config(MAX_MINUTES, MAX_MINUTES).expire_after().is_ok());
+    }
+
+    #[test]
+    fn test_validate() {
+        let config = || Config {
+            url: Some("https://tenki.jp/forecast/3/16/4410/13120".to_string()),
+            ..Config::default()
+        };
+        assert!(config().validate().is_ok());
+
+        let invalid = [
+            Config {
+                lang: Some("fr".to_string()),
+                ..Config::default()
+            },
+            Config {
+                unit: Some("kelvin".to_string()),
+                ..Config::default()
+            },
+            Config {
+                template: Some("{unknown}".to_string()),
+                ..Config::default()
+            },
+            Config {
+                emoji_file: Some(PathBuf::from("/nonexistent/emoji.toml")),
+                ..Config::default()
+            },
+            Config {
+                interval: Some(0),
+                ..Config::default()
+            },
+        ];
+        for x in invalid {
+            assert!(config().merge(x).validate().is_err());
+        }
+    }
+
+    #[test]
+    fn test_from_toml_unknown_field() {
+        let result = Config::from_toml(Path::new("config.toml"), r#"tokn = "xoxp-token""#);
+
+        assert!(matches!(result, Err(Error::InvalidFile(..))));
+    }
+
+    #[test]
+    fn test_destination() {
+        let config = Config {
+            token: Some("xoxp-token".to_string()),
+            notify_channel: Some("#weather".to_string()),
+            ..Config::default()
+        };
+        assert_eq!(
+            config.destination().unwrap(),
+            Some(Destination::Channel {
+                token: "xoxp-token".to_string(),
+                channel: "#weather".to_string(),
+            })
+        );
+
+        let config = Config {
+            notify_channel: Some("#weather".to_string()),
+            ..Config::default()
+        };
+        assert!(matches!(config.destination(), Err(Error::InvalidConfig(_))));
+
+        let config = Config {
+            notify_webhook: Some("https://hooks.slack.com/services/T/B/X".to_string()),
+            ..Config::default()
+        };
+        assert_eq!(
+            config.destination().unwrap(),
+            Some(Destination::Webhook(
+                "https://hooks.slack.com/services/T/B/X".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_post_to() {
+        let config = Config {
+            token: Some("xoxp-token".to_string()),
+            post_to: Some("#general".to_string()),
+            post_only: Some(true),
+            ..Config::default()
+        };
+        assert_eq!(
+            config.post_to().unwrap(),
+            Some(Destination::Channel {
+                token: "xoxp-token".to_string(),
+                channel: "#general".to_string(),
+            })
+        );
+        assert!(config.post_only().unwrap());
+
+        let config = Config {
+            post_only: Some(true),
+            ..Config::default()
+        };
+        assert!(matches!(config.post_only(), Err(Error::InvalidConfig(_))));
+
+        let config = Config {
+            post_hour: Some(24),
+            ..Config::default()
+        };
+        assert!(matches!(config.post_hour(), Err(Error::InvalidConfig(_))));
+    }
+
+    #[test]
+    fn test_merge() {
+        let file = Config {
+            url: Some("file url".to_string()),
+            token: Some("file token".to_string()),
+            retries: Some(5),
+            ..Config::default()
+        };
+        let cli = Config {
+            token: Some("cli token".to_string()),
+            ..Config::default()
+        };
+        let config = file.merge(cli);
+
+        assert_eq!(config.url().unwrap(), "file url");
+        assert_eq!(config.token.as_deref(), Some("cli token"));
+        assert_eq!(config.retry_policy().max_retries, 5);
+    }
+
+    mod test_users {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn top_level() {
+            let config = Config {
+                url: Some("url".to_string()),
+                token: Some("token".to_string()),
+                ..Config::default()
+            };
+
+            assert_eq!(
+                config.users().unwrap(),
+                vec![User {
+                    name: DEFAULT_KEY.to_string(),
+                    url: "url".to_string(),
+                    token: Some("token".to_string()),
+                    template: Template::default(),
+                }]
+            );
+        }
+
+        #[test]
+        fn profiles() {
+            let config = Config::from_toml(
+                Path::new("config.toml"),
+                r#"
+url = "https://tenki.jp/forecast/3/16/4410/13113"
+
+[[profiles]]
+name = "alice"
+token = "xoxp-alice"
+
+[[profiles]]
+name = "bob"
+url = "https://tenki.jp/forecast/3/16/4410/13120"
+token = "xoxp-bob"
+template = "{weather} {high_temp}℃"
+"#,
+            )
+            .unwrap();
+
+            assert_eq!(
+                config.users().unwrap(),
+                vec![
+                    User {
+                        name: "alice".to_string(),
+                        url: "https://tenki.jp/forecast/3/16/4410/13113".to_string(),
+                        token: Some("xoxp-alice".to_string()),
+                        template: Template::default(),
+                    },
+                    User {
+                        name: "bob".to_string(),
+                        url: "https://tenki.jp/forecast/3/16/4410/13120".to_string(),
+                        token: Some("xoxp-bob".to_string()),
+                        template: Template::parse("{weather} {high_temp}℃").unwrap(),
+                    },
+                ]
+            );
+        }
+
+        #[test]
+        fn duplicate_name() {
+            let profile = || ProfileConfig {
+                name: "alice".to_string(),
+                ..ProfileConfig::default()
+            };
+            let config = Config {
+                url: Some("url".to_string()),
+                profiles: vec![profile(), profile()],
+                ..Config::default()
+            };
+
+            assert!(matches!(
+                config.users(),
+                Err(Error::InvalidConfig(x)) if x == "duplicate profile name: alice"
+            ));
+        }
+
+        #[test]
+        fn no_url() {
+            let config = Config {
+                profiles: vec![ProfileConfig {
+                    name: "alice".to_string(),
+                    ..ProfileConfig::default()
+                }],
+                ..Config::default()
+            };
+
+            assert!(matches!(config.users(), Err(Error::InvalidConfig(_))));
+        }
+
+        #[test]
+        fn invalid_template() {
+            let config = Config {
+                url: Some("url".to_string()),
+                template: Some("{temp}".to_string()),
+                ..Config::default()
+            };
+
+            assert!(matches!(config.users(), Err(Error::InvalidTemplate(_))));
+        }
+    }
+
+    #[test]
+    fn test_defaults() {
+        let config = Config::default();
+
+        assert!(matches!(config.url(), Err(Error::InvalidConfig(_))));
+        assert_eq!(config.day().unwrap(), Day::Today);
+        assert_eq!(
+            config.expire_after().unwrap(),
+            Some(Duration::from_secs(7200))
+        );
+        assert!(!config.rain());
+        assert!(!config.keep_manual_status());
+        assert_eq!(config.max_length(), 100);
+        assert_eq!(config.locale().unwrap(), Locale::Ja);
+        assert_eq!(config.unit().unwrap(), TempUnit::Celsius);
+        assert_eq!(config.ranking().unwrap(), Ranking::default());
+        assert_eq!(config.alert_only().unwrap(), None);
+        assert_eq!(config.destination().unwrap(), None);
+        assert_eq!(config.notify_level().unwrap(), Severity::Warning);
+        assert_eq!(config.post_to().unwrap(), None);
+        assert!(!config.post_only().unwrap());
+        assert_eq!(config.post_hour().unwrap(), 7);
+        assert_eq!(config.template().unwrap(), Template::default());
+        assert_eq!(
+            config.schedule().unwrap().interval,
+            Duration::from_secs(600)
+        );
+        assert!(config.validate().is_err()); // no url
+    }
+}
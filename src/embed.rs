@@ -4,8 +4,11 @@ use lazy_static::lazy_static;
 use rust_embed::RustEmbed;
 use std::str::from_utf8;
 
+use crate::locale::Translation;
+
 const ALERT_EMOJI_MAP_TOML: &str = "alert_emoji_map.toml";
 const WEATHER_EMOJI_MAP_TOML: &str = "weather_emoji_map.toml";
+const LOCALE_EN_TOML: &str = "locale_en.toml";
 
 #[derive(RustEmbed)]
 #[folder = "embed/"]
@@ -14,6 +17,7 @@ struct Embed;
 lazy_static! {
     pub static ref ALERT_EMOJI_MAP: HashMap<String, String> = init_alert_emoji_map();
     pub static ref WEATHER_EMOJI_MAP: HashMap<String, String> = init_weather_emoji_map();
+    pub static ref EN_TRANSLATION: Translation = init_en_translation();
 }
 
 fn init_alert_emoji_map() -> HashMap<String, String> {
@@ -28,6 +32,12 @@ fn init_weather_emoji_map() -> HashMap<String, String> {
     toml::from_str(raw).expect("weather_emoji_map couldn't parse.")
 }
 
+fn init_en_translation() -> Translation {
+    let file = Embed::get(LOCALE_EN_TOML).expect("locale_en not found.");
+    let raw = from_utf8(file.as_ref()).expect("locale_en couldn't open.");
+    toml::from_str(raw).expect("locale_en couldn't parse.")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -44,4 +54,11 @@ mod tests {
         assert_eq!(WEATHER_EMOJI_MAP.get("01").unwrap(), ":sunny:");
         assert_eq!(WEATHER_EMOJI_MAP.get("02").unwrap(), ":mostly_sunny:");
     }
+
+    #[test]
+    fn test_en_translation() {
+        for key in ALERT_EMOJI_MAP.keys() {
+            assert!(EN_TRANSLATION.alerts.contains_key(key), "{key}");
+        }
+    }
 }
@@ -0,0 +1,310 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::embed::{ALERT_EMOJI_MAP, WEATHER_EMOJI_MAP};
+use crate::error::{Error, Result};
+
+/// The weather icons of tenki.jp, which are 01 to 30.
+const WEATHER_ICON_COUNT: u8 = 30;
+const NIGHT_SUFFIX: &str = "_n";
+const DEFAULT_WEATHER_FALLBACK: &str = ":grey_question:";
+const DEFAULT_ALERT_FALLBACK: &str = ":warning:";
+
+/// Emoji of the weather icons and the alerts. The default is the embedded maps.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EmojiMaps {
+    pub alerts: HashMap<String, String>,
+    pub weather: HashMap<String, String>,
+    pub fallback: Fallback,
+}
+
+/// Emoji of a weather icon or an alert which isn't in the maps, e.g. a new one of tenki.jp.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Fallback {
+    pub weather: Option<String>,
+    pub alerts: Option<String>,
+}
+
+/// The keys which didn't have emoji.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UnknownEmoji {
+    pub weather: BTreeSet<String>,
+    pub alerts: BTreeSet<String>,
+}
+
+impl Default for EmojiMaps {
+    fn default() -> Self {
+        Self {
+            alerts: ALERT_EMOJI_MAP.clone(),
+            weather: WEATHER_EMOJI_MAP.clone(),
+            fallback: Fallback::default(),
+        }
+    }
+}
+
+impl EmojiMaps {
+    /// Reads an override file which has `[alerts]` and `[weather]` tables, and merges it over the embedded maps.
+    pub fn load(path: &Path) -> Result<Self> {
+        let raw = fs::read_to_string(path)?;
+        let overrides = toml::from_str::<Self>(&raw)
+            .map_err(|e| Error::InvalidFile(path.to_path_buf(), e.to_string()))?;
+        let emoji_maps = Self::default().merge(overrides);
+        emoji_maps
+            .validate()
+            .map_err(|e| Error::InvalidFile(path.to_path_buf(), e))?;
+        Ok(emoji_maps)
+    }
+
+    /// Entries of `other` take precedence.
+    pub fn merge(mut self, other: Self) -> Self {
+        self.alerts.extend(other.alerts);
+        self.weather.extend(other.weather);
+        self.fallback = Fallback {
+            weather: other.fallback.weather.or(self.fallback.weather),
+            alerts: other.fallback.alerts.or(self.fallback.alerts),
+        };
+        self
+    }
+
+    /// Every weather icon and every known alert has an emoji like `:name:`.
+    fn validate(&self) -> std::result::Result<(), String> {
+        let icons = (1..=WEATHER_ICON_COUNT).map(|x| format!("{x:02}"));
+        for icon in icons {
+            if !self.weather.contains_key(&icon) {
+                return Err(format!("no emoji of {icon}"));
+            }
+        }
+
+        for alert in ALERT_EMOJI_MAP.keys() {
+            if !self.alerts.contains_key(alert) {
+                return Err(format!("no emoji of {alert}"));
+            }
+        }
+
+        for (key, emoji) in self.weather.iter().chain(&self.alerts) {
+            if !is_emoji(emoji) {
+                return Err(format!("invalid emoji of {key}: {emoji:?}"));
+            }
+        }
+
+        for emoji in [self.weather_fallback(), self.alert_fallback()] {
+            if !is_emoji(emoji) {
+                return Err(format!("invalid fallback emoji: {emoji:?}"));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn alert(&self, key: &str) -> Option<&str> {
+        self.alerts.get(key).map(String::as_str)
+    }
+
+    /// A night icon like `01_n` falls back to the day one when it doesn't have its own emoji.
+    pub fn weather(&self, icon_name: &str) -> Option<&str> {
+        self.weather
+            .get(icon_name)
+            .or_else(|| {
+                icon_name
+                    .strip_suffix(NIGHT_SUFFIX)
+                    .and_then(|x| self.weather.get(x))
+            })
+            .map(String::as_str)
+    }
+
+    pub fn weather_fallback(&self) -> &str {
+        self.fallback
+            .weather
+            .as_deref()
+            .unwrap_or(DEFAULT_WEATHER_FALLBACK)
+    }
+
+    pub fn alert_fallback(&self) -> &str {
+        self.fallback
+            .alerts
+            .as_deref()
+            .unwrap_or(DEFAULT_ALERT_FALLBACK)
+    }
+
+    /// Whether the emoji is one of the maps, the embedded maps or the fallbacks, i.e. one that this tool sets.
+    /// The embedded ones count as well, since the tool may have set them before the override file.
+    pub fn contains(&self, emoji: &str) -> bool {
+        self.alerts
+            .values()
+            .chain(self.weather.values())
+            .chain(ALERT_EMOJI_MAP.values())
+            .chain(WEATHER_EMOJI_MAP.values())
+            .map(String::as_str)
+            .chain([self.weather_fallback(), self.alert_fallback()])
+            .any(|x| x == emoji)
+    }
+}
+
+impl UnknownEmoji {
+    pub fn is_empty(&self) -> bool {
+        self.weather.is_empty() && self.alerts.is_empty()
+    }
+
+    pub fn extend(&mut self, other: Self) {
+        self.weather.extend(other.weather);
+        self.alerts.extend(other.alerts);
+    }
+
+    /// Drops the keys which have emoji now, e.g. after they were added to the override file.
+    pub fn retain_unknown(&mut self, emoji_maps: &EmojiMaps) {
+        self.weather.retain(|x| emoji_maps.weather(x).is_none());
+        self.alerts.retain(|x| emoji_maps.alert(x).is_none());
+    }
+
+    /// An error which lists the keys, so that `--list-unknown` exits with a non-zero code.
+    pub fn check(&self) -> Result<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+
+        let keys = self
+            .weather
+            .iter()
+            .map(|x| format!("weather: {x}"))
+            .chain(self.alerts.iter().map(|x| format!("alerts: {x}")))
+            .collect::<Vec<_>>();
+        Err(Error::UnknownEmoji(keys.join(", ")))
+    }
+}
+
+impl Display for UnknownEmoji {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for key in &self.weather {
+            writeln!(f, "weather: {key}")?;
+        }
+        for key in &self.alerts {
+            writeln!(f, "alerts: {key}")?;
+        }
+        Ok(())
+    }
+}
+
+/// e.g. `:sunny:` or `:tenki_hare:`
+fn is_emoji(emoji: &str) -> bool {
+    emoji.len() > 2
+        && emoji.starts_with(':')
+        && emoji.ends_with(':')
+        && !emoji[1..emoji.len() - 1].contains([':', ' '])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn from_toml(raw: &str) -> EmojiMaps {
+        EmojiMaps::default().merge(toml::from_str(raw).unwrap())
+    }
+
+    #[test]
+    fn test_merge() {
+        let emoji_maps = from_toml(
+            r#"
+[weather]
+"01" = ":tenki_hare:"
+
+[alerts]
+"大雨" = ":tenki_ooame:"
+"#,
+        );
+
+        assert_eq!(emoji_maps.weather("01"), Some(":tenki_hare:"));
+        assert_eq!(emoji_maps.weather("02"), Some(":mostly_sunny:"));
+        assert_eq!(emoji_maps.alert("大雨"), Some(":tenki_ooame:"));
+        assert_eq!(emoji_maps.alert("雷"), Some(":zap:"));
+        assert!(emoji_maps.contains(":tenki_hare:"));
+        // The embedded one which was set before the override
+        assert!(emoji_maps.contains(":sunny:"));
+        assert!(!emoji_maps.contains(":palm_tree:"));
+    }
+
+    #[test]
+    fn test_fallback() {
+        let emoji_maps = from_toml(r#"fallback = { weather = ":tenki:" }"#);
+
+        assert_eq!(emoji_maps.weather_fallback(), ":tenki:");
+        assert_eq!(emoji_maps.alert_fallback(), ":warning:");
+        assert!(emoji_maps.contains(":warning:"));
+    }
+
+    #[test]
+    fn test_night_weather() {
+        let emoji_maps = EmojiMaps::default();
+
+        assert_eq!(emoji_maps.weather("01"), Some(":sunny:"));
+        assert_eq!(emoji_maps.weather("01_n"), Some(":crescent_moon:"));
+        assert_eq!(emoji_maps.weather("05_n"), Some(":crescent_moon:"));
+        assert_eq!(emoji_maps.weather("08_n"), Some(":cloud:"));
+        assert_eq!(emoji_maps.weather("10_n"), Some(":umbrella:"));
+        assert_eq!(emoji_maps.weather("15_n"), Some(":umbrella:"));
+        assert_eq!(emoji_maps.weather("99_n"), None);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(EmojiMaps::default().validate(), Ok(()));
+        assert_eq!(
+            from_toml(r#"weather = { "03" = "" }"#).validate(),
+            Err(r#"invalid emoji of 03: """#.to_string())
+        );
+        assert_eq!(
+            from_toml(r#"alerts = { "雷" = "zap" }"#).validate(),
+            Err(r#"invalid emoji of 雷: "zap""#.to_string())
+        );
+
+        assert_eq!(
+            from_toml(r#"fallback = { weather = "?" }"#).validate(),
+            Err(r#"invalid fallback emoji: "?""#.to_string())
+        );
+
+        let mut emoji_maps = EmojiMaps::default();
+        emoji_maps.weather.remove("30");
+        assert_eq!(emoji_maps.validate(), Err("no emoji of 30".to_string()));
+    }
+
+    #[test]
+    fn test_check_unknown() {
+        assert!(UnknownEmoji::default().check().is_ok());
+
+        let unknown_emoji = UnknownEmoji {
+            weather: ["31".to_string()].into(),
+            alerts: ["新しい".to_string()].into(),
+        };
+        assert!(matches!(
+            unknown_emoji.check(),
+            Err(Error::UnknownEmoji(x)) if x == "weather: 31, alerts: 新しい"
+        ));
+    }
+
+    #[test]
+    fn test_retain_unknown() {
+        let mut unknown_emoji = UnknownEmoji {
+            weather: ["31".to_string(), "32".to_string()].into(),
+            alerts: ["新しい".to_string()].into(),
+        };
+        unknown_emoji.retain_unknown(&from_toml(
+            r#"
+[weather]
+"31" = ":tenki_31:"
+"32" = ":tenki_32:"
+
+[alerts]
+"新しい" = ":warning:"
+"#,
+        ));
+
+        assert_eq!(unknown_emoji, UnknownEmoji::default());
+        assert!(unknown_emoji.check().is_ok());
+    }
+}
@@ -0,0 +1,96 @@
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use reqwest::StatusCode;
+
+use crate::slack::SlackError;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Couldn't connect, timed out or couldn't read a response.
+    Network(reqwest::Error),
+    /// The server responded with a non-2xx status.
+    HttpStatus(StatusCode),
+    /// The page doesn't have an expected element. It usually means that tenki.jp changed its layout.
+    MissingSelector(String),
+    /// The weather icon doesn't have a file name.
+    InvalidIconPath(String),
+    /// A number on the page couldn't be parsed.
+    ParseNumber(String),
+    /// Emoji maps don't have the keys, e.g. `weather: 31`.
+    UnknownEmoji(String),
+    /// Slack API responded with `ok: false` or 429.
+    Slack(SlackError),
+    /// Couldn't read or write a local file.
+    Io(io::Error),
+    /// A local file couldn't be parsed or serialized.
+    InvalidFile(PathBuf, String),
+    /// A setting is missing or has an invalid value.
+    InvalidConfig(String),
+    /// A status text template has an unknown placeholder or unbalanced braces.
+    InvalidTemplate(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Network(e) => write!(f, "network error: {e}"),
+            Self::HttpStatus(status) => write!(f, "unexpected HTTP status: {status}"),
+            Self::MissingSelector(selector) => write!(f, "selector not found: {selector}"),
+            Self::InvalidIconPath(path) => write!(f, "invalid weather icon path: {path:?}"),
+            Self::ParseNumber(text) => write!(f, "couldn't parse a number: {text:?}"),
+            Self::UnknownEmoji(keys) => write!(f, "no emoji for: {keys}"),
+            Self::Slack(e) => write!(f, "Slack API error: {e}"),
+            Self::Io(e) => write!(f, "I/O error: {e}"),
+            Self::InvalidFile(path, message) => write!(f, "invalid {}: {message}", path.display()),
+            Self::InvalidConfig(message) => write!(f, "invalid config: {message}"),
+            Self::InvalidTemplate(message) => write!(f, "invalid template: {message}"),
+        }
+    }
+}
+
+impl Error {
+    /// Whether the request may succeed when it's sent again, i.e. 5xx, a timeout or rate limiting.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Network(e) => e.is_timeout(),
+            Self::HttpStatus(status) => status.is_server_error(),
+            Self::Slack(SlackError::RateLimited(_)) => true,
+            _ => false,
+        }
+    }
+
+    /// How long the server asked to wait before retrying.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::Slack(SlackError::RateLimited(retry_after)) => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Network(e) => Some(e),
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Self::Network(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
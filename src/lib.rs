@@ -1,3 +1,15 @@
+pub mod blocks;
+pub mod config;
 pub mod embed;
+pub mod emoji;
+pub mod error;
+pub mod locale;
 pub mod models;
+pub mod retry;
+pub mod runner;
+pub mod schedule;
 pub mod slack;
+pub mod state;
+pub mod template;
+
+pub use error::{Error, Result};
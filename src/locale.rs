@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use crate::embed::EN_TRANSLATION;
+use crate::error::Result;
+use crate::models::{Advisory, Severity};
+use crate::template::Template;
+
+/// The language of the status text. The page of tenki.jp is in Japanese, so `Ja` doesn't translate anything.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Locale {
+    #[default]
+    Ja,
+    En,
+}
+
+/// Words of tenki.jp and the labels of the status text in another language.
+#[derive(Debug, Deserialize)]
+pub struct Translation {
+    pub template: String,
+    pub date_time: String, // `{day}` and `{time}` of e.g. 30日16:00
+    pub advisory_separator: String,
+    pub list_separator: String,
+    pub issued: String,                      // 発表 of an advisory
+    pub lifted: String,                      // 解除 of an advisory
+    pub high: String,                        // 最高 of the forecast card
+    pub low: String,                         // 最低 of the forecast card
+    pub rain: String,                        // 降水確率 of the forecast card
+    pub advisories: HashMap<String, String>, // 特別警報, 警報 and 注意報
+    pub alerts: HashMap<String, String>,     // the keys of alert_emoji_map.toml
+    pub telops: HashMap<String, String>,
+}
+
+impl FromStr for Locale {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "ja" => Ok(Self::Ja),
+            "en" => Ok(Self::En),
+            x => Err(format!("unknown lang: {x}")),
+        }
+    }
+}
+
+impl Locale {
+    fn translation(&self) -> Option<&'static Translation> {
+        match self {
+            Self::Ja => None,
+            Self::En => Some(&EN_TRANSLATION),
+        }
+    }
+
+    pub fn default_template(&self) -> Result<Template> {
+        match self.translation() {
+            Some(translation) => translation.template.parse(),
+            None => Ok(Template::default()),
+        }
+    }
+
+    /// e.g. 大雨警報 or Heavy Rain Warning.
+    pub fn advisory(&self, advisory: &Advisory) -> String {
+        let name = advisory.name();
+        match self.translation() {
+            Some(x) => format!(
+                "{}{}{}",
+                x.alerts.get(name).map(String::as_str).unwrap_or(name),
+                x.advisory_separator,
+                self.level(advisory.level)
+            ),
+            None => format!("{name}{}", advisory.level.name()),
+        }
+    }
+
+    pub fn level(&self, level: Severity) -> String {
+        self.translation()
+            .and_then(|x| x.advisories.get(level.name()))
+            .map(String::as_str)
+            .unwrap_or(level.name())
+            .to_string()
+    }
+
+    pub fn list_separator(&self) -> &str {
+        self.translation()
+            .map(|x| x.list_separator.as_str())
+            .unwrap_or(",")
+    }
+
+    pub fn issued(&self) -> &str {
+        self.translation()
+            .map(|x| x.issued.as_str())
+            .unwrap_or("発表")
+    }
+
+    pub fn lifted(&self) -> &str {
+        self.translation()
+            .map(|x| x.lifted.as_str())
+            .unwrap_or("解除")
+    }
+
+    pub fn high(&self) -> &str {
+        self.translation()
+            .map(|x| x.high.as_str())
+            .unwrap_or("最高")
+    }
+
+    pub fn low(&self) -> &str {
+        self.translation().map(|x| x.low.as_str()).unwrap_or("最低")
+    }
+
+    pub fn rain(&self) -> &str {
+        self.translation()
+            .map(|x| x.rain.as_str())
+            .unwrap_or("降水確率")
+    }
+
+    /// Translates the words of a telop which are known, and leaves the others.
+    pub fn telop(&self, telop: &str) -> String {
+        let Some(translation) = self.translation() else {
+            return telop.to_string();
+        };
+
+        let mut text = String::new();
+        let mut rest = telop;
+        while let Some(c) = rest.chars().next() {
+            // The longest word wins, e.g. 大雨 over 雨.
+            let word = translation
+                .telops
+                .iter()
+                .filter(|(word, _)| rest.starts_with(word.as_str()))
+                .max_by_key(|(word, _)| word.len());
+            match word {
+                Some((word, x)) => {
+                    text.push_str(x);
+                    rest = &rest[word.len()..];
+                }
+                None => {
+                    text.push(c);
+                    rest = &rest[c.len_utf8()..];
+                }
+            }
+        }
+
+        capitalize(text.trim())
+    }
+
+    /// e.g. 30日16:00 is "16:00 on day 30".
+    pub fn date_time(&self, date_time: &str) -> String {
+        match (self.translation(), date_time.split_once('日')) {
+            (Some(x), Some((day, time))) => {
+                x.date_time.replace("{day}", day).replace("{time}", time)
+            }
+            _ => date_time.to_string(),
+        }
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(c) => c.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_telop() {
+        assert_eq!(Locale::En.telop("晴時々曇"), "Sunny, occasionally cloudy");
+        assert_eq!(Locale::En.telop("大雨のち晴"), "Heavy rain, then sunny");
+        assert_eq!(Locale::En.telop("雨か雪"), "Rain or snow");
+        assert_eq!(Locale::Ja.telop("晴時々曇"), "晴時々曇");
+    }
+
+    #[test]
+    fn test_advisory() {
+        assert_eq!(
+            Locale::En.advisory(&Advisory::new("大雨", Severity::Warning)),
+            "Heavy Rain Warning"
+        );
+        assert_eq!(
+            Locale::En.advisory(&Advisory::new("新しい", Severity::Alert)),
+            "新しい Advisory"
+        );
+        assert_eq!(
+            Locale::Ja.advisory(&Advisory::new("大雨", Severity::Warning)),
+            "大雨警報"
+        );
+    }
+
+    #[test]
+    fn test_labels() {
+        assert_eq!(Locale::En.high(), "High");
+        assert_eq!(Locale::Ja.high(), "最高");
+        assert_eq!(Locale::En.rain(), "Chance of rain");
+        assert_eq!(Locale::Ja.lifted(), "解除");
+    }
+
+    #[test]
+    fn test_date_time() {
+        assert_eq!(Locale::En.date_time("30日16:00"), "16:00 on day 30");
+        assert_eq!(Locale::Ja.date_time("30日16:00"), "30日16:00");
+    }
+
+    #[test]
+    fn test_default_template() {
+        assert!(Locale::En.default_template().is_ok());
+    }
+}
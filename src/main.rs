@@ -2,23 +2,36 @@ extern crate clap;
 #[macro_use]
 extern crate log;
 
-use clap::{App, Arg};
-use rusty_slack_weather_status::models::tenki_jp_forecast::TenkiJpForecast;
-use rusty_slack_weather_status::slack::SlackRequest;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use chrono::Utc;
+use clap::{App, Arg, ArgMatches};
+use rusty_slack_weather_status::config::Config;
+use rusty_slack_weather_status::runner::{Runner, Summary};
+use rusty_slack_weather_status::schedule::{jst, parse_published_at};
+use rusty_slack_weather_status::state::State;
+use tokio::signal::unix::{SignalKind, signal};
+use tokio::time::delay_for;
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn main() {
     env_logger::init();
 
     let matches = App::new("Rusty Slack Weather Status")
         .version("0.99")
         .about("Set the weather on your Slack status")
+        .arg(
+            Arg::with_name("CONFIG")
+                .short("c")
+                .long("config")
+                .help("Sets a config file. Default: ~/.config/rusty-slack-weather-status/config.toml")
+                .takes_value(true))
         .arg(
             Arg::with_name("URL")
                 .short("u")
                 .long("url")
-                .help("Sets a tenki.jp url. e.g.) https://tenki.jp/forecast/3/16/4410/13113")
-                .required(true)
+                .help("Sets a tenki.jp url. e.g.) https://tenki.jp/forecast/3/16/4410/13113 Default: $TENKI_JP_URL")
                 .takes_value(true))
         .arg(
             Arg::with_name("DRY RUN")
@@ -26,40 +39,356 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .long("dry")
                 .help("Don't send to the Slack.")
         )
+        .arg(
+            Arg::with_name("DAY")
+                .long("day")
+                .help("Sets a day of the forecast. \"auto\" switches to tomorrow after the evening hour. Default: today")
+                .possible_values(&["today", "tomorrow", "auto"])
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("EVENING HOUR")
+                .long("evening-hour")
+                .help("Sets an hour to switch to tomorrow's forecast in the auto mode. Default: 18")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("RAIN PROBABILITY")
+                .short("r")
+                .long("rain")
+                .help("Shows the precipitation chance of the current or next time slot.")
+        )
+        .arg(
+            Arg::with_name("NO RAIN PROBABILITY")
+                .long("no-rain")
+                .help("Doesn't show the precipitation chance, even if the config file sets rain.")
+                .conflicts_with("RAIN PROBABILITY")
+        )
+        .arg(
+            Arg::with_name("TEMPLATE")
+                .long("template")
+                .help("Sets a format of the status text. e.g.) \"{place}: {weather} {high_temp}{temp_unit}{?precip} ☔{precip}%{/precip}\"")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("LANG")
+                .long("lang")
+                .help("Sets a language of the status text. Default: ja")
+                .possible_values(&["ja", "en"])
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("UNIT")
+                .long("unit")
+                .help("Sets a unit of temperatures. Default: celsius")
+                .possible_values(&["celsius", "fahrenheit"])
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("EMOJI FILE")
+                .long("emoji-file")
+                .help("Sets a TOML file of emoji which overrides the embedded ones. e.g.) [weather] \"01\" = \":tenki_hare:\"")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("PRIORITY")
+                .long("priority")
+                .help("Sets comma-separated phenomena whose emoji and text come first among the same level. e.g.) 雷,大雨")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("MUTE")
+                .long("mute")
+                .help("Sets comma-separated phenomena which are ignored. e.g.) 乾燥,濃霧")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("ALERT ONLY")
+                .long("alert-only")
+                .help("Sets the status only while there is an advisory of the level or more severe: alert, warning or special_warning")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("ALERT ONLY ACTION")
+                .long("alert-only-action")
+                .help("Sets what is done to the status without such an advisory: clear or keep. Default: clear")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("NOTIFY CHANNEL")
+                .long("notify-channel")
+                .help("Posts the issued and lifted advisories to the channel with the token. It needs chat:write scope. e.g.) #weather")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("NOTIFY WEBHOOK")
+                .long("notify-webhook")
+                .help("Posts the issued and lifted advisories to an incoming webhook URL")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("NOTIFY LEVEL")
+                .long("notify-level")
+                .help("Sets the level of the advisories which are posted, and the more severe ones: alert, warning or special_warning. Default: warning")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("POST TO")
+                .long("post-to")
+                .help("Posts a forecast card to the channel with the token once a day. It needs chat:write scope. e.g.) #general")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("POST ONLY")
+                .long("post-only")
+                .help("Posts the forecast card without setting the status")
+        )
+        .arg(
+            Arg::with_name("NO POST ONLY")
+                .long("no-post-only")
+                .help("Sets the status as well, even if the config file sets post_only.")
+                .conflicts_with("POST ONLY")
+        )
+        .arg(
+            Arg::with_name("POST HOUR")
+                .long("post-hour")
+                .help("Sets the hour in JST from which the forecast card of the day is posted. Default: 7")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("MAX LENGTH")
+                .long("max-length")
+                .help("Sets max characters of the status text. A longer text is abbreviated. Default: 100")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("EXPIRE AFTER")
+                .long("expire-after")
+                .help("Sets minutes after which Slack clears the status. 0 means never. Default: 120")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("KEEP MANUAL STATUS")
+                .long("keep-manual-status")
+                .help("Doesn't overwrite a status which was set by hand. It needs users.profile:read scope.")
+        )
+        .arg(
+            Arg::with_name("NO KEEP MANUAL STATUS")
+                .long("no-keep-manual-status")
+                .help("Overwrites a status which was set by hand, even if the config file sets keep_manual_status.")
+                .conflicts_with("KEEP MANUAL STATUS")
+        )
+        .arg(
+            Arg::with_name("STATE FILE")
+                .long("state-file")
+                .help("Sets a file to save the last status. Default: ~/.local/state/rusty-slack-weather-status/state.toml")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("LIST UNKNOWN")
+                .long("list-unknown")
+                .help("Prints the weather icons and the alerts which didn't have emoji so far, and exits. It fails if there is any. They are saved in the state file.")
+        )
+        .arg(
+            Arg::with_name("FORCE")
+                .short("f")
+                .long("force")
+                .help("Sends the status even if it's unchanged since the last time.")
+        )
+        .arg(
+            Arg::with_name("RETRIES")
+                .long("retries")
+                .help("Sets a number of retries on 5xx responses, timeouts and rate limiting. Default: 3")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("RETRY BACKOFF")
+                .long("retry-backoff")
+                .help("Sets seconds to wait before the first retry. It doubles on every retry. Default: 1")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("TIMEOUT")
+                .long("timeout")
+                .help("Sets a timeout in seconds of each request. Default: 10")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("DAEMON")
+                .long("daemon")
+                .help("Keeps running and refreshes the status periodically, instead of crontab. It stops on SIGTERM.")
+        )
+        .arg(
+            Arg::with_name("INTERVAL")
+                .long("interval")
+                .help("Sets minutes between refreshes in the daemon mode. They are aligned to the publication time of tenki.jp. Default: 10")
+                .takes_value(true)
+        )
+        .arg(
+            Arg::with_name("JITTER")
+                .long("jitter")
+                .help("Sets max seconds of a random delay added to each refresh in the daemon mode. Default: 30")
+                .takes_value(true)
+        )
         .arg(
             Arg::with_name("SLACK_TOKEN")
                 .short("t")
                 .long("token")
                 .help(
-                    "Sets a slack token. e.g.) xoxp-***********-************-************-********************************")
-                .required(true)
+                    "Sets a slack token. e.g.) xoxp-***********-************-************-******************************** Default: $SLACK_TOKEN")
                 .takes_value(true)
         )
         .get_matches();
 
-    let tenki_jp_url = matches.value_of("URL").unwrap();
-    let tenki_jp_forecast = TenkiJpForecast::get(tenki_jp_url).await?;
-    let forecast = tenki_jp_forecast.parse()?;
-
-    let is_dry_run = matches.is_present("DRY RUN");
-    match is_dry_run {
-        true => {
-            println!("{forecast:?}");
-            println!(
-                "{:?}, {:?}",
-                &forecast.build_emoji(),
-                &forecast.build_text()
-            )
+    if let Err(e) = run(&matches).await {
+        error!("{e}");
+        std::process::exit(1);
+    }
+}
+
+/// The config file < environment variables < CLI options
+fn load_config(matches: &ArgMatches<'_>) -> Result<Config, Box<dyn std::error::Error>> {
+    let file_config = match matches.value_of("CONFIG") {
+        Some(path) => Config::load(&PathBuf::from(path))?,
+        None => match Config::default_path() {
+            Some(path) => Config::load_if_exists(&path)?,
+            None => Config::default(),
+        },
+    };
+
+    let cli_config = Config {
+        url: matches.value_of("URL").map(|x| x.to_string()),
+        token: matches.value_of("SLACK_TOKEN").map(|x| x.to_string()),
+        day: matches.value_of("DAY").map(|x| x.to_string()),
+        evening_hour: parse_value_of(matches, "EVENING HOUR")?,
+        rain: flag_of(matches, "RAIN PROBABILITY", "NO RAIN PROBABILITY"),
+        template: matches.value_of("TEMPLATE").map(|x| x.to_string()),
+        max_length: parse_value_of(matches, "MAX LENGTH")?,
+        lang: matches.value_of("LANG").map(|x| x.to_string()),
+        unit: matches.value_of("UNIT").map(|x| x.to_string()),
+        emoji_file: matches.value_of("EMOJI FILE").map(PathBuf::from),
+        priority: split_value_of(matches, "PRIORITY"),
+        mute: split_value_of(matches, "MUTE"),
+        alert_only: matches.value_of("ALERT ONLY").map(|x| x.to_string()),
+        alert_only_action: matches.value_of("ALERT ONLY ACTION").map(|x| x.to_string()),
+        notify_channel: matches.value_of("NOTIFY CHANNEL").map(|x| x.to_string()),
+        notify_webhook: matches.value_of("NOTIFY WEBHOOK").map(|x| x.to_string()),
+        notify_level: matches.value_of("NOTIFY LEVEL").map(|x| x.to_string()),
+        post_to: matches.value_of("POST TO").map(|x| x.to_string()),
+        post_only: flag_of(matches, "POST ONLY", "NO POST ONLY"),
+        post_hour: parse_value_of(matches, "POST HOUR")?,
+        expire_after: parse_value_of(matches, "EXPIRE AFTER")?,
+        keep_manual_status: flag_of(matches, "KEEP MANUAL STATUS", "NO KEEP MANUAL STATUS"),
+        state_file: matches.value_of("STATE FILE").map(PathBuf::from),
+        retries: parse_value_of(matches, "RETRIES")?,
+        retry_backoff: parse_value_of(matches, "RETRY BACKOFF")?,
+        timeout: parse_value_of(matches, "TIMEOUT")?,
+        interval: parse_value_of(matches, "INTERVAL")?,
+        jitter: parse_value_of(matches, "JITTER")?,
+        profiles: vec![],
+    };
+
+    Ok(file_config.merge(Config::from_env()).merge(cli_config))
+}
+
+fn parse_value_of<T>(
+    matches: &ArgMatches<'_>,
+    name: &str,
+) -> Result<Option<T>, Box<dyn std::error::Error>>
+where
+    T: FromStr,
+    T::Err: std::error::Error + 'static,
+{
+    Ok(matches.value_of(name).map(|x| x.parse()).transpose()?)
+}
+
+/// `--xxx` is true and `--no-xxx` is false, so the CLI can turn off the option of the config file.
+fn flag_of(matches: &ArgMatches<'_>, name: &str, negated_name: &str) -> Option<bool> {
+    if matches.is_present(name) {
+        Some(true)
+    } else if matches.is_present(negated_name) {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// e.g. `乾燥,濃霧`
+fn split_value_of(matches: &ArgMatches<'_>, name: &str) -> Option<Vec<String>> {
+    matches
+        .value_of(name)
+        .map(|x| x.split(',').map(|x| x.trim().to_string()).collect())
+}
+
+async fn run(matches: &ArgMatches<'_>) -> Result<(), Box<dyn std::error::Error>> {
+    let runner = Runner {
+        config: load_config(matches)?,
+        force: matches.is_present("FORCE"),
+        dry_run: matches.is_present("DRY RUN"),
+    };
+    if matches.is_present("LIST UNKNOWN") {
+        if let Some(path) = runner.config.state_path() {
+            // The keys which were added to the emoji file since aren't listed.
+            let mut unknown_emoji = State::load(&path)?.unknown_emoji;
+            unknown_emoji.retain_unknown(&runner.config.emoji_maps()?);
+            print!("{unknown_emoji}");
+            unknown_emoji.check()?;
         }
-        false => {
-            let token = matches.value_of("SLACK_TOKEN").unwrap();
-            let slack_request = SlackRequest::new(token);
-            let (_status_code, res) = slack_request
-                .update_status(&forecast.build_emoji(), &forecast.build_text())
-                .await?;
-            info!("{:?}", res);
+        return Ok(());
+    }
+    // Invalid configs never recover, so they stop even the daemon at once.
+    runner.config.validate()?;
+    if matches.is_present("DAEMON") {
+        return serve(&runner).await;
+    }
+
+    let summary = runner.run().await?;
+    log_summary(&runner, &summary);
+    if !summary.is_success() {
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+fn log_summary(runner: &Runner, summary: &Summary) {
+    match (runner.dry_run, summary.is_success()) {
+        (true, _) => print!("{summary}"),
+        (false, true) => info!("{summary}"),
+        (false, false) => error!("{summary}"),
+    }
+}
+
+/// Runs until SIGTERM or Ctrl-C. A run in progress is finished before stopping, so the state is saved.
+async fn serve(runner: &Runner) -> Result<(), Box<dyn std::error::Error>> {
+    let schedule = runner.config.schedule()?;
+    let mut sigterm = signal(SignalKind::terminate())?;
+    loop {
+        let published_at = match runner.run().await {
+            Ok(summary) => {
+                log_summary(runner, &summary);
+                let now = Utc::now().with_timezone(&jst());
+                summary
+                    .date_times
+                    .iter()
+                    .filter_map(|x| parse_published_at(x, now))
+                    .max()
+            }
+            Err(e) => {
+                error!("{e}");
+                None
+            }
+        };
+
+        let delay = schedule.delay(Utc::now().with_timezone(&jst()), published_at);
+        info!("The next run is in {delay:?}.");
+        tokio::select! {
+            _ = delay_for(delay) => {}
+            _ = sigterm.recv() => break,
+            _ = tokio::signal::ctrl_c() => break,
         }
     }
 
+    info!("Stopped.");
     Ok(())
 }
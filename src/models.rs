@@ -1,7 +1,19 @@
+pub mod advisory;
+pub mod day;
 pub mod forecast;
+pub mod rain_probability;
 pub mod temp_diff;
+pub mod temp_unit;
 pub mod tenki_jp_forecast;
+pub mod weekly_forecast;
+pub mod wind_wave;
 
+pub use advisory::*;
+pub use day::*;
 pub use forecast::*;
+pub use rain_probability::*;
 pub use temp_diff::*;
+pub use temp_unit::*;
 pub use tenki_jp_forecast::*;
+pub use weekly_forecast::*;
+pub use wind_wave::*;
@@ -0,0 +1,405 @@
+use std::cmp::{Ordering, Reverse};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::Forecast;
+
+/// The level of an advisory. The later one is the more severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Alert,          // 注意報
+    Warning,        // 警報
+    SpecialWarning, // 特別警報
+}
+
+impl Severity {
+    /// The suffix of tenki.jp, e.g. 大雨警報.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Alert => "注意報",
+            Self::Warning => "警報",
+            Self::SpecialWarning => "特別警報",
+        }
+    }
+}
+
+impl FromStr for Severity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "alert" => Ok(Self::Alert),
+            "warning" => Ok(Self::Warning),
+            "special_warning" => Ok(Self::SpecialWarning),
+            x => Err(format!("unknown level: {x}")),
+        }
+    }
+}
+
+/// A phenomenon of JMA, which is a key of alert_emoji_map.toml. It's saved as the name.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum AlertKind {
+    HeavyRain,      // 大雨
+    Storm,          // 暴風
+    Snowstorm,      // 暴風雪
+    HeavySnow,      // 大雪
+    Flood,          // 洪水
+    StormSurge,     // 高潮
+    HighWaves,      // 波浪
+    WindAndSnow,    // 風雪
+    Gale,           // 強風
+    Thunderstorm,   // 雷
+    Avalanche,      // なだれ
+    Snowmelt,       // 融雪
+    Icing,          // 着氷
+    SnowAccretion,  // 着雪
+    DenseFog,       // 濃霧
+    LowTemperature, // 低温
+    Frost,          // 霜
+    DryAir,         // 乾燥
+    Other(String),  // a new one of tenki.jp
+}
+
+/// The known phenomena in the default priority, from the most dangerous one.
+const KINDS: [(&str, AlertKind); 18] = [
+    ("大雨", AlertKind::HeavyRain),
+    ("暴風", AlertKind::Storm),
+    ("暴風雪", AlertKind::Snowstorm),
+    ("大雪", AlertKind::HeavySnow),
+    ("洪水", AlertKind::Flood),
+    ("高潮", AlertKind::StormSurge),
+    ("波浪", AlertKind::HighWaves),
+    ("風雪", AlertKind::WindAndSnow),
+    ("強風", AlertKind::Gale),
+    ("雷", AlertKind::Thunderstorm),
+    ("なだれ", AlertKind::Avalanche),
+    ("融雪", AlertKind::Snowmelt),
+    ("着氷", AlertKind::Icing),
+    ("着雪", AlertKind::SnowAccretion),
+    ("濃霧", AlertKind::DenseFog),
+    ("低温", AlertKind::LowTemperature),
+    ("霜", AlertKind::Frost),
+    ("乾燥", AlertKind::DryAir),
+];
+
+impl AlertKind {
+    /// The name of tenki.jp, e.g. 大雨.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Other(x) => x,
+            kind => KINDS.iter().find(|(_, x)| x == kind).unwrap().0,
+        }
+    }
+}
+
+impl From<&str> for AlertKind {
+    fn from(name: &str) -> Self {
+        KINDS
+            .iter()
+            .find(|(x, _)| *x == name)
+            .map(|(_, x)| x.clone())
+            .unwrap_or_else(|| Self::Other(name.to_string()))
+    }
+}
+
+impl From<String> for AlertKind {
+    fn from(name: String) -> Self {
+        name.as_str().into()
+    }
+}
+
+impl From<AlertKind> for String {
+    fn from(kind: AlertKind) -> Self {
+        kind.name().to_string()
+    }
+}
+
+/// A 特別警報, 警報 or 注意報 of a phenomenon, e.g. 大雨警報.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Advisory {
+    pub kind: AlertKind,
+    pub level: Severity,
+}
+
+impl Advisory {
+    pub fn new(name: &str, level: Severity) -> Self {
+        Self {
+            kind: name.into(),
+            level,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.kind.name()
+    }
+}
+
+/// How the advisories are ranked: by the level, and then by the priority of the phenomena.
+/// `priority` comes before the default priority, and `mute` is dropped.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Ranking {
+    pub priority: Vec<AlertKind>,
+    pub mute: Vec<AlertKind>,
+}
+
+impl Ranking {
+    /// Unknown phenomena come last, in the order of tenki.jp.
+    pub fn apply(&self, mut advisories: Vec<Advisory>) -> Vec<Advisory> {
+        advisories.retain(|x| !self.mute.contains(&x.kind));
+        advisories.sort_by_key(|x| (Reverse(x.level), self.priority_of(&x.kind)));
+        advisories
+    }
+
+    fn priority_of(&self, kind: &AlertKind) -> usize {
+        match self.priority.iter().position(|x| x == kind) {
+            Some(i) => i,
+            None => {
+                self.priority.len()
+                    + KINDS
+                        .iter()
+                        .position(|(_, x)| x == kind)
+                        .unwrap_or(KINDS.len())
+            }
+        }
+    }
+}
+
+/// Sets the status only while there are advisories of `min_level` or more severe.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AlertOnly {
+    pub min_level: Severity,
+    pub action: NoAlertAction,
+}
+
+/// What is done to the status when there is no advisory of the level.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum NoAlertAction {
+    #[default]
+    Clear,
+    Keep,
+}
+
+impl FromStr for NoAlertAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "clear" => Ok(Self::Clear),
+            "keep" => Ok(Self::Keep),
+            x => Err(format!("unknown action: {x}")),
+        }
+    }
+}
+
+impl AlertOnly {
+    pub fn is_alerting(&self, forecast: &Forecast) -> bool {
+        forecast.max_severity() >= Some(self.min_level)
+    }
+}
+
+/// The advisories of `min_level` or more severe which were issued, lifted or changed their level since
+/// the last time.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AdvisoryChanges {
+    pub issued: Vec<Advisory>,
+    pub lifted: Vec<Advisory>,
+    pub upgraded: Vec<LevelChange>,   // e.g. 大雨警報 to 大雨特別警報
+    pub downgraded: Vec<LevelChange>, // e.g. 大雨警報 to 大雨注意報
+}
+
+/// An advisory of a phenomenon which is still in effect, but at another level.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelChange {
+    pub from: Advisory,
+    pub to: Advisory,
+}
+
+impl AdvisoryChanges {
+    /// The advisories are matched by the phenomenon, so that a change of the level isn't an issued and
+    /// a lifted one. A change counts when either level is `min_level` or more severe.
+    pub fn diff(previous: &[Advisory], current: &[Advisory], min_level: Severity) -> Self {
+        let find = |advisories: &[Advisory], kind: &AlertKind| {
+            advisories.iter().find(|x| x.kind == *kind).cloned()
+        };
+        let mut changes = Self::default();
+        for x in current {
+            match find(previous, &x.kind) {
+                None if x.level >= min_level => changes.issued.push(x.clone()),
+                Some(from) if from.level.max(x.level) >= min_level => {
+                    let change = LevelChange {
+                        from: from.clone(),
+                        to: x.clone(),
+                    };
+                    match x.level.cmp(&from.level) {
+                        Ordering::Greater => changes.upgraded.push(change),
+                        Ordering::Less => changes.downgraded.push(change),
+                        Ordering::Equal => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+        changes.lifted = previous
+            .iter()
+            .filter(|x| x.level >= min_level && find(current, &x.kind).is_none())
+            .cloned()
+            .collect();
+        changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.issued.is_empty()
+            && self.lifted.is_empty()
+            && self.upgraded.is_empty()
+            && self.downgraded.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_severity() {
+        assert_eq!("warning".parse(), Ok(Severity::Warning));
+        assert!("警報".parse::<Severity>().is_err());
+        assert!(Severity::SpecialWarning > Severity::Warning);
+        assert!(Severity::Warning > Severity::Alert);
+    }
+
+    #[test]
+    fn test_kind() {
+        assert_eq!(AlertKind::from("大雨"), AlertKind::HeavyRain);
+        assert_eq!(
+            AlertKind::from("新しい"),
+            AlertKind::Other("新しい".to_string())
+        );
+        assert_eq!(AlertKind::Thunderstorm.name(), "雷");
+        assert_eq!(AlertKind::Other("新しい".to_string()).name(), "新しい");
+    }
+
+    mod test_ranking {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        fn advisories() -> Vec<Advisory> {
+            vec![
+                Advisory::new("乾燥", Severity::Alert),
+                Advisory::new("新しい", Severity::Alert),
+                Advisory::new("雷", Severity::Alert),
+                Advisory::new("洪水", Severity::Warning),
+                Advisory::new("大雨", Severity::SpecialWarning),
+            ]
+        }
+
+        #[test]
+        fn default() {
+            assert_eq!(
+                Ranking::default().apply(advisories()),
+                vec![
+                    Advisory::new("大雨", Severity::SpecialWarning),
+                    Advisory::new("洪水", Severity::Warning),
+                    Advisory::new("雷", Severity::Alert),
+                    Advisory::new("乾燥", Severity::Alert),
+                    Advisory::new("新しい", Severity::Alert),
+                ]
+            );
+        }
+
+        #[test]
+        fn priority() {
+            let ranking = Ranking {
+                priority: vec!["乾燥".into(), "大雨".into()],
+                ..Ranking::default()
+            };
+
+            assert_eq!(
+                ranking.apply(advisories()),
+                vec![
+                    Advisory::new("大雨", Severity::SpecialWarning),
+                    Advisory::new("洪水", Severity::Warning),
+                    Advisory::new("乾燥", Severity::Alert),
+                    Advisory::new("雷", Severity::Alert),
+                    Advisory::new("新しい", Severity::Alert),
+                ]
+            );
+        }
+
+        #[test]
+        fn mute() {
+            let ranking = Ranking {
+                mute: vec!["乾燥".into(), "新しい".into()],
+                ..Ranking::default()
+            };
+
+            assert_eq!(
+                ranking.apply(advisories()),
+                vec![
+                    Advisory::new("大雨", Severity::SpecialWarning),
+                    Advisory::new("洪水", Severity::Warning),
+                    Advisory::new("雷", Severity::Alert),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_changes() {
+        let previous = vec![
+            Advisory::new("大雨", Severity::Warning),
+            Advisory::new("洪水", Severity::Warning),
+            Advisory::new("暴風", Severity::Warning),
+            Advisory::new("波浪", Severity::Warning),
+            Advisory::new("雷", Severity::Alert),
+        ];
+        let current = vec![
+            Advisory::new("大雨", Severity::SpecialWarning),
+            Advisory::new("暴風", Severity::Alert),
+            Advisory::new("波浪", Severity::Warning),
+            Advisory::new("高潮", Severity::Warning),
+            Advisory::new("乾燥", Severity::Alert),
+        ];
+
+        assert_eq!(
+            AdvisoryChanges::diff(&previous, &current, Severity::Warning),
+            AdvisoryChanges {
+                issued: vec![Advisory::new("高潮", Severity::Warning)],
+                lifted: vec![Advisory::new("洪水", Severity::Warning)],
+                upgraded: vec![LevelChange {
+                    from: Advisory::new("大雨", Severity::Warning),
+                    to: Advisory::new("大雨", Severity::SpecialWarning),
+                }],
+                downgraded: vec![LevelChange {
+                    from: Advisory::new("暴風", Severity::Warning),
+                    to: Advisory::new("暴風", Severity::Alert),
+                }],
+            }
+        );
+        assert!(AdvisoryChanges::diff(&current, &current, Severity::Alert).is_empty());
+    }
+
+    #[test]
+    fn test_changes_below_min_level() {
+        let previous = vec![Advisory::new("雷", Severity::Alert)];
+        let current = vec![
+            Advisory::new("雷", Severity::Warning),
+            Advisory::new("乾燥", Severity::Alert),
+        ];
+
+        assert_eq!(
+            AdvisoryChanges::diff(&previous, &current, Severity::Warning),
+            AdvisoryChanges {
+                upgraded: vec![LevelChange {
+                    from: Advisory::new("雷", Severity::Alert),
+                    to: Advisory::new("雷", Severity::Warning),
+                }],
+                ..AdvisoryChanges::default()
+            }
+        );
+        assert!(AdvisoryChanges::diff(&current, &previous, Severity::SpecialWarning).is_empty());
+    }
+}
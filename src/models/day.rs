@@ -0,0 +1,64 @@
+use std::str::FromStr;
+
+/// Which day of the forecast page to build a Forecast from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Day {
+    Today,
+    Tomorrow,
+    Auto { evening_hour: u32 }, // Today until the evening hour, then Tomorrow
+}
+
+/// A day of the forecast page, i.e. `Day` whose `Auto` is resolved at an hour.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResolvedDay {
+    Today,
+    Tomorrow,
+}
+
+impl Day {
+    pub fn resolve(self, hour: u32) -> ResolvedDay {
+        match self {
+            Self::Today => ResolvedDay::Today,
+            Self::Tomorrow => ResolvedDay::Tomorrow,
+            Self::Auto { evening_hour } if hour >= evening_hour => ResolvedDay::Tomorrow,
+            Self::Auto { .. } => ResolvedDay::Today,
+        }
+    }
+}
+
+impl ResolvedDay {
+    pub(crate) fn section_selector(self) -> &'static str {
+        match self {
+            Self::Today => "section.today-weather",
+            Self::Tomorrow => "section.tomorrow-weather",
+        }
+    }
+}
+
+impl FromStr for Day {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "today" => Ok(Self::Today),
+            "tomorrow" => Ok(Self::Tomorrow),
+            _ => Err(format!("unknown day: {s}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_resolve() {
+        let auto = Day::Auto { evening_hour: 18 };
+
+        assert_eq!(auto.resolve(17), ResolvedDay::Today);
+        assert_eq!(auto.resolve(18), ResolvedDay::Tomorrow);
+        assert_eq!(Day::Today.resolve(23), ResolvedDay::Today);
+        assert_eq!(Day::Tomorrow.resolve(0), ResolvedDay::Tomorrow);
+    }
+}
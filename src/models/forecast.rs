@@ -1,117 +1,275 @@
-use crate::embed::{ALERT_EMOJI_MAP, WEATHER_EMOJI_MAP};
+use log::warn;
+
+use crate::emoji::{EmojiMaps, UnknownEmoji};
+use crate::error::Result;
+use crate::locale::Locale;
+use crate::models::advisory::{Advisory, AlertKind, Severity};
+use crate::models::rain_probability::RainProbability;
 use crate::models::temp_diff::TempDiff;
+use crate::models::temp_unit::TempUnit;
+use crate::models::wind_wave::{Wave, Wind};
+use crate::template::{Field, Template};
 
 #[derive(Debug)]
 pub struct Forecast {
     pub place: String,
     pub date_time: String,
-    pub special_warnings: Option<Vec<String>>, // 特別警報
-    pub warnings: Option<Vec<String>>,         // 警報
-    pub alerts: Option<Vec<String>>,           // 注意報
+    pub advisories: Vec<Advisory>, // ranked from the most severe one
     pub weather: String,
     pub weather_icon_name: String,
     pub high_temp: i16,
     pub high_temp_diff: TempDiff,
     pub low_temp: i16,
     pub low_temp_diff: TempDiff,
+    pub rain_probability: RainProbability, // 降水確率
+    pub wind: Option<Wind>,
+    pub wave: Option<Wave>,
 }
 
 impl Forecast {
     pub fn build_emoji(&self) -> String {
-        match self.has_alert_text() {
-            Some(x) => self.build_alert_emoji(x),
-            None => self.build_weather_emoji(),
-        }
+        self.build_emoji_with(&EmojiMaps::default())
     }
 
-    fn has_alert_text(&self) -> Option<&String> {
-        if let Some(special_warnings) = &self.special_warnings {
-            return special_warnings.first();
+    pub fn build_emoji_with(&self, emoji_maps: &EmojiMaps) -> String {
+        match self.advisories.first() {
+            Some(x) => self.build_alert_emoji(emoji_maps, x.name()),
+            None if self.has_strong_wind() => {
+                self.build_alert_emoji(emoji_maps, AlertKind::Gale.name())
+            }
+            None => self.build_weather_emoji(emoji_maps),
         }
+    }
+
+    fn has_strong_wind(&self) -> bool {
+        self.wind.as_ref().is_some_and(Wind::is_strong)
+    }
 
-        if let Some(warnings) = &self.warnings {
-            return warnings.first();
+    /// The most severe level of the advisories, if any.
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.advisories.first().map(|x| x.level)
+    }
+
+    fn advisories_of(&self, level: Severity) -> Vec<&Advisory> {
+        self.advisories
+            .iter()
+            .filter(|x| x.level == level)
+            .collect()
+    }
+
+    fn build_alert_emoji(&self, emoji_maps: &EmojiMaps, alert_text: &str) -> String {
+        match emoji_maps.alert(alert_text) {
+            Some(x) => x.to_string(),
+            None => {
+                let fallback = emoji_maps.alert_fallback();
+                warn!("no emoji for the alert {alert_text}, {fallback} is used");
+                fallback.to_string()
+            }
         }
+    }
 
-        if let Some(alerts) = &self.alerts {
-            return alerts.first();
+    pub fn build_weather_emoji(&self, emoji_maps: &EmojiMaps) -> String {
+        match emoji_maps.weather(&self.weather_icon_name) {
+            Some(x) => x.to_string(),
+            None => {
+                let fallback = emoji_maps.weather_fallback();
+                warn!(
+                    "no emoji for the weather icon {}, {fallback} is used",
+                    self.weather_icon_name
+                );
+                fallback.to_string()
+            }
         }
+    }
 
-        None
+    /// The weather icon and the alerts of the forecast which don't have emoji.
+    pub fn unknown_emoji(&self, emoji_maps: &EmojiMaps) -> UnknownEmoji {
+        let alerts = self
+            .advisories
+            .iter()
+            .map(Advisory::name)
+            .chain(self.has_strong_wind().then(|| AlertKind::Gale.name()));
+
+        UnknownEmoji {
+            weather: emoji_maps
+                .weather(&self.weather_icon_name)
+                .is_none()
+                .then(|| self.weather_icon_name.clone())
+                .into_iter()
+                .collect(),
+            alerts: alerts
+                .filter(|x| emoji_maps.alert(x).is_none())
+                .map(|x| x.to_string())
+                .collect(),
+        }
     }
 
-    fn build_alert_emoji(&self, alert_text: &String) -> String {
-        ALERT_EMOJI_MAP.get(alert_text).unwrap().to_string()
+    pub fn build_text(&self) -> Result<String> {
+        self.build_text_with(&TextOptions::default(), None)
     }
 
-    fn build_weather_emoji(&self) -> String {
-        let weather_icon_num = self.weather_icon_name.replace("_n", "");
-        WEATHER_EMOJI_MAP
-            .get(&weather_icon_num)
-            .unwrap()
-            .to_string()
+    /// Same as `build_text`, with the 降水確率 of the current or next time slot of `hour`.
+    pub fn build_text_at(&self, hour: u32) -> Result<String> {
+        self.build_text_with(&TextOptions::default(), self.rain_probability.at(hour))
     }
 
-    pub fn build_text(&self) -> String {
-        let advisory_text = match self.special_warnings.is_some()
-            || self.warnings.is_some()
-            || self.alerts.is_some()
-        {
-            true => {
-                let special_warnings = self.special_warnings.as_ref().map(|x| {
-                    x.iter()
-                        .map(|special_warning| format!("{special_warning}特別警報"))
-                        .collect::<Vec<_>>()
-                        .join(",")
-                });
-
-                let warnings = self.warnings.as_ref().map(|x| {
-                    x.iter()
-                        .map(|warning| format!("{warning}警報"))
-                        .collect::<Vec<_>>()
-                        .join(",")
-                });
-
-                let alerts = self.alerts.as_ref().map(|x| {
-                    x.iter()
-                        .map(|alert| format!("{alert}注意報"))
-                        .collect::<Vec<_>>()
-                        .join(",")
-                });
-
-                let texts = vec![special_warnings, warnings, alerts]
-                    .into_iter()
-                    .flatten()
-                    .collect::<Vec<_>>()
-                    .join(",");
-
-                Some(texts)
+    /// `rain_probability` is shown as `{precip}`, which is empty when it's `None`.
+    /// The text is abbreviated step by step until it fits in `max_length` characters, and truncated at last.
+    pub fn build_text_with(
+        &self,
+        options: &TextOptions,
+        rain_probability: Option<u8>,
+    ) -> Result<String> {
+        let mut text = String::new();
+        for abbreviation in Abbreviation::STEPS {
+            text = self.render(options, rain_probability, abbreviation)?;
+            if text.chars().count() <= options.max_length {
+                return Ok(text);
             }
-            false => None,
+        }
+
+        Ok(truncate(&text, options.max_length))
+    }
+
+    fn render(
+        &self,
+        options: &TextOptions,
+        rain_probability: Option<u8>,
+        abbreviation: Abbreviation,
+    ) -> Result<String> {
+        let locale = options.locale;
+        let unit = options.unit;
+        let no_diffs = abbreviation >= Abbreviation::NoDiffs;
+        let collapsed = abbreviation >= Abbreviation::CollapsedAdvisories;
+        let join = |level, collapsed| {
+            join_advisories(&self.advisories_of(level), level, collapsed, locale)
         };
-        let weather_text = format!(
-            "{w} 最高: {ht}℃[{htd}] 最低: {lt}℃[{ltd}] 発表: {dt}",
-            w = self.weather,
-            ht = self.high_temp,
-            htd = self.high_temp_diff,
-            lt = self.low_temp,
-            ltd = self.low_temp_diff,
-            dt = self.date_time
-        );
+        let special_warnings = join(Severity::SpecialWarning, false);
+        let warnings = join(Severity::Warning, collapsed);
+        let alerts = join(Severity::Alert, collapsed);
+
+        options.template.render(|field| match field {
+            Field::Place => Ok(self.place.clone()),
+            Field::Weather => Ok(locale.telop(&self.weather)),
+            Field::WeatherEmoji => Ok(self.build_weather_emoji(&options.emoji_maps)),
+            Field::HighTemp => Ok(unit.convert(self.high_temp).to_string()),
+            Field::HighTempDiff if no_diffs => Ok("".to_string()),
+            Field::HighTempDiff => Ok(self.high_temp_diff.convert(unit).to_string()),
+            Field::LowTemp => Ok(unit.convert(self.low_temp).to_string()),
+            Field::LowTempDiff if no_diffs => Ok("".to_string()),
+            Field::LowTempDiff => Ok(self.low_temp_diff.convert(unit).to_string()),
+            Field::TempUnit => Ok(unit.symbol().to_string()),
+            Field::DateTime if abbreviation >= Abbreviation::NoDateTime => Ok("".to_string()),
+            Field::DateTime => Ok(locale.date_time(&self.date_time)),
+            Field::SpecialWarnings => Ok(special_warnings.clone()),
+            Field::Warnings => Ok(warnings.clone()),
+            Field::Alerts => Ok(alerts.clone()),
+            Field::Advisories => Ok([&special_warnings, &warnings, &alerts]
+                .into_iter()
+                .filter(|x| !x.is_empty())
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(locale.list_separator())),
+            Field::Precip => Ok(rain_probability.map(|x| x.to_string()).unwrap_or_default()),
+        })
+    }
+}
 
-        match advisory_text.is_some() {
-            true => format!(
-                "{}: {} {}: {}",
-                self.place,
-                advisory_text.unwrap(),
-                self.build_weather_emoji(),
-                weather_text
-            ),
-            false => format!("{}: {}", self.place, weather_text),
+/// How a status text is built.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextOptions {
+    pub template: Template,
+    pub max_length: usize, // characters
+    pub locale: Locale,
+    pub unit: TempUnit,
+    pub emoji_maps: EmojiMaps,
+}
+
+impl Default for TextOptions {
+    fn default() -> Self {
+        Self {
+            template: Template::default(),
+            max_length: usize::MAX,
+            locale: Locale::default(),
+            unit: TempUnit::default(),
+            emoji_maps: EmojiMaps::default(),
         }
     }
 }
 
+/// How much a status text is shortened. Each step includes the previous ones.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+enum Abbreviation {
+    None,
+    NoDateTime,          // drops 発表
+    NoDiffs,             // drops the differences from yesterday's temperatures
+    CollapsedAdvisories, // e.g. 注意報×3
+}
+
+impl Abbreviation {
+    const STEPS: [Self; 4] = [
+        Self::None,
+        Self::NoDateTime,
+        Self::NoDiffs,
+        Self::CollapsedAdvisories,
+    ];
+}
+
+/// Cuts before a shortcode or an ASCII word which doesn't fit, so that e.g. `:umbrella:` isn't left as `:umbrell…`.
+fn truncate(text: &str, max_length: usize) -> String {
+    let chars = text.chars().collect::<Vec<_>>();
+    if chars.len() <= max_length {
+        return text.to_string();
+    }
+
+    let mut end = max_length.saturating_sub(1);
+    if let Some(start) = shortcode_start(&chars, end) {
+        end = start;
+    } else if end > 0 && is_word_char(chars[end - 1]) && is_word_char(chars[end]) {
+        // A single word which is longer than the text is still cut.
+        end = match chars[..end].iter().rposition(|x| !is_word_char(*x)) {
+            Some(x) => x + 1,
+            None => end,
+        };
+    }
+
+    let text = chars[..end].iter().collect::<String>();
+    format!("{}…", text.trim_end())
+}
+
+/// The index of the opening colon when `end` is inside a shortcode, e.g. 0 when `:umbrella:` is cut at 4.
+fn shortcode_start(chars: &[char], end: usize) -> Option<usize> {
+    let is_name = |x: &&char| x.is_ascii_alphanumeric() || "_+-".contains(**x);
+    let before = chars[..end].iter().rev().take_while(is_name).count();
+    let after = chars[end..].iter().take_while(is_name).count();
+    let opening = (end - before).checked_sub(1)?;
+    match (chars[opening], chars.get(end + after)) {
+        (':', Some(':')) if before + after > 0 => Some(opening),
+        _ => None,
+    }
+}
+
+fn is_word_char(x: char) -> bool {
+    x.is_ascii_alphanumeric()
+}
+
+/// e.g. `大雨警報,洪水警報`, or `警報×2` when it's collapsed.
+fn join_advisories(
+    advisories: &[&Advisory],
+    level: Severity,
+    collapsed: bool,
+    locale: Locale,
+) -> String {
+    match advisories {
+        x if collapsed && x.len() > 1 => format!("{}×{}", locale.level(level), x.len()),
+        x => x
+            .iter()
+            .map(|x| locale.advisory(x))
+            .collect::<Vec<_>>()
+            .join(locale.list_separator()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -125,15 +283,16 @@ mod tests {
             let forecast = Forecast {
                 place: "場所".to_string(),
                 date_time: "日時".to_string(),
-                special_warnings: None,
-                warnings: None,
-                alerts: None,
+                advisories: vec![],
                 weather: "晴".to_string(),
                 weather_icon_name: "01".to_string(),
                 high_temp: 10,
-                high_temp_diff: TempDiff::new("3"),
+                high_temp_diff: TempDiff::new("3").unwrap(),
                 low_temp: 0,
-                low_temp_diff: TempDiff::new("-5"),
+                low_temp_diff: TempDiff::new("-5").unwrap(),
+                rain_probability: RainProbability::default(),
+                wind: None,
+                wave: None,
             };
 
             assert_eq!(forecast.build_emoji(), ":sunny:");
@@ -144,15 +303,21 @@ mod tests {
             let forecast = Forecast {
                 place: "場所".to_string(),
                 date_time: "日時".to_string(),
-                special_warnings: Some(vec!["大雨".to_string()]),
-                warnings: Some(vec!["洪水".to_string()]),
-                alerts: Some(vec!["強風".to_string(), "雷".to_string()]),
+                advisories: vec![
+                    Advisory::new("大雨", Severity::SpecialWarning),
+                    Advisory::new("洪水", Severity::Warning),
+                    Advisory::new("強風", Severity::Alert),
+                    Advisory::new("雷", Severity::Alert),
+                ],
                 weather: "晴".to_string(),
                 weather_icon_name: "01".to_string(),
                 high_temp: 10,
-                high_temp_diff: TempDiff::new("3"),
+                high_temp_diff: TempDiff::new("3").unwrap(),
                 low_temp: 0,
-                low_temp_diff: TempDiff::new("-5"),
+                low_temp_diff: TempDiff::new("-5").unwrap(),
+                rain_probability: RainProbability::default(),
+                wind: None,
+                wave: None,
             };
 
             assert_eq!(forecast.build_emoji(), ":bucket:");
@@ -163,15 +328,20 @@ mod tests {
             let forecast = Forecast {
                 place: "場所".to_string(),
                 date_time: "日時".to_string(),
-                special_warnings: None,
-                warnings: Some(vec!["大雨".to_string()]),
-                alerts: Some(vec!["洪水".to_string(), "雷".to_string()]),
+                advisories: vec![
+                    Advisory::new("大雨", Severity::Warning),
+                    Advisory::new("洪水", Severity::Alert),
+                    Advisory::new("雷", Severity::Alert),
+                ],
                 weather: "晴".to_string(),
                 weather_icon_name: "01".to_string(),
                 high_temp: 10,
-                high_temp_diff: TempDiff::new("3"),
+                high_temp_diff: TempDiff::new("3").unwrap(),
                 low_temp: 0,
-                low_temp_diff: TempDiff::new("-5"),
+                low_temp_diff: TempDiff::new("-5").unwrap(),
+                rain_probability: RainProbability::default(),
+                wind: None,
+                wave: None,
             };
 
             assert_eq!(forecast.build_emoji(), ":bucket:");
@@ -182,19 +352,140 @@ mod tests {
             let forecast = Forecast {
                 place: "場所".to_string(),
                 date_time: "日時".to_string(),
-                special_warnings: None,
-                warnings: None,
-                alerts: Some(vec!["洪水".to_string(), "雷".to_string()]),
+                advisories: vec![
+                    Advisory::new("洪水", Severity::Alert),
+                    Advisory::new("雷", Severity::Alert),
+                ],
                 weather: "晴".to_string(),
                 weather_icon_name: "01".to_string(),
                 high_temp: 10,
-                high_temp_diff: TempDiff::new("3"),
+                high_temp_diff: TempDiff::new("3").unwrap(),
                 low_temp: 0,
-                low_temp_diff: TempDiff::new("-5"),
+                low_temp_diff: TempDiff::new("-5").unwrap(),
+                rain_probability: RainProbability::default(),
+                wind: None,
+                wave: None,
             };
 
             assert_eq!(forecast.build_emoji(), ":ocean:");
         }
+
+        #[test]
+        fn strong_wind() {
+            let forecast = Forecast {
+                place: "場所".to_string(),
+                date_time: "日時".to_string(),
+                advisories: vec![],
+                weather: "晴".to_string(),
+                weather_icon_name: "01".to_string(),
+                high_temp: 10,
+                high_temp_diff: TempDiff::new("3").unwrap(),
+                low_temp: 0,
+                low_temp_diff: TempDiff::new("-5").unwrap(),
+                rain_probability: RainProbability::default(),
+                wind: Some(Wind::new("北の風強く")),
+                wave: None,
+            };
+
+            assert_eq!(forecast.build_emoji(), ":wind_blowing_face:");
+        }
+
+        #[test]
+        fn unknown_weather_icon() {
+            let forecast = Forecast {
+                place: "場所".to_string(),
+                date_time: "日時".to_string(),
+                advisories: vec![],
+                weather: "晴".to_string(),
+                weather_icon_name: "99".to_string(),
+                high_temp: 10,
+                high_temp_diff: TempDiff::new("3").unwrap(),
+                low_temp: 0,
+                low_temp_diff: TempDiff::new("-5").unwrap(),
+                rain_probability: RainProbability::default(),
+                wind: None,
+                wave: None,
+            };
+
+            assert_eq!(forecast.build_emoji(), ":grey_question:");
+            assert_eq!(
+                forecast.unknown_emoji(&EmojiMaps::default()),
+                UnknownEmoji {
+                    weather: ["99".to_string()].into(),
+                    alerts: [].into(),
+                }
+            );
+        }
+
+        #[test]
+        fn unknown_alert() {
+            let forecast = Forecast {
+                place: "場所".to_string(),
+                date_time: "日時".to_string(),
+                advisories: vec![
+                    Advisory::new("新しい", Severity::Alert),
+                    Advisory::new("雷", Severity::Alert),
+                ],
+                weather: "晴".to_string(),
+                weather_icon_name: "01".to_string(),
+                high_temp: 10,
+                high_temp_diff: TempDiff::new("3").unwrap(),
+                low_temp: 0,
+                low_temp_diff: TempDiff::new("-5").unwrap(),
+                rain_probability: RainProbability::default(),
+                wind: None,
+                wave: None,
+            };
+
+            assert_eq!(forecast.build_emoji(), ":warning:");
+            assert_eq!(
+                forecast.unknown_emoji(&EmojiMaps::default()),
+                UnknownEmoji {
+                    weather: [].into(),
+                    alerts: ["新しい".to_string()].into(),
+                }
+            );
+        }
+
+        #[test]
+        fn night() {
+            let forecast = Forecast {
+                place: "場所".to_string(),
+                date_time: "日時".to_string(),
+                advisories: vec![],
+                weather: "晴".to_string(),
+                weather_icon_name: "01_n".to_string(),
+                high_temp: 10,
+                high_temp_diff: TempDiff::new("3").unwrap(),
+                low_temp: 0,
+                low_temp_diff: TempDiff::new("-5").unwrap(),
+                rain_probability: RainProbability::default(),
+                wind: None,
+                wave: None,
+            };
+
+            assert_eq!(forecast.build_emoji(), ":crescent_moon:");
+        }
+
+        #[test]
+        fn night_fallback() {
+            let forecast = Forecast {
+                place: "場所".to_string(),
+                date_time: "日時".to_string(),
+                advisories: vec![],
+                weather: "雨".to_string(),
+                weather_icon_name: "15_n".to_string(),
+                high_temp: 10,
+                high_temp_diff: TempDiff::new("3").unwrap(),
+                low_temp: 0,
+                low_temp_diff: TempDiff::new("-5").unwrap(),
+                rain_probability: RainProbability::default(),
+                wind: None,
+                wave: None,
+            };
+
+            assert_eq!(forecast.build_emoji(), ":umbrella:");
+        }
     }
 
     mod test_build_text {
@@ -206,19 +497,20 @@ mod tests {
             let forecast = Forecast {
                 place: "場所".to_string(),
                 date_time: "日時".to_string(),
-                special_warnings: None,
-                warnings: None,
-                alerts: None,
+                advisories: vec![],
                 weather: "晴".to_string(),
                 weather_icon_name: "01".to_string(),
                 high_temp: 10,
-                high_temp_diff: TempDiff::new("3"),
+                high_temp_diff: TempDiff::new("3").unwrap(),
                 low_temp: 0,
-                low_temp_diff: TempDiff::new("-5"),
+                low_temp_diff: TempDiff::new("-5").unwrap(),
+                rain_probability: RainProbability::default(),
+                wind: None,
+                wave: None,
             };
 
             assert_eq!(
-                forecast.build_text(),
+                forecast.build_text().unwrap(),
                 "場所: 晴 最高: 10℃[+3] 最低: 0℃[-5] 発表: 日時"
             );
         }
@@ -228,19 +520,25 @@ mod tests {
             let forecast = Forecast {
                 place: "場所".to_string(),
                 date_time: "日時".to_string(),
-                special_warnings: Some(vec!["大雨".to_string()]),
-                warnings: Some(vec!["洪水".to_string()]),
-                alerts: Some(vec!["強風".to_string(), "雷".to_string()]),
+                advisories: vec![
+                    Advisory::new("大雨", Severity::SpecialWarning),
+                    Advisory::new("洪水", Severity::Warning),
+                    Advisory::new("強風", Severity::Alert),
+                    Advisory::new("雷", Severity::Alert),
+                ],
                 weather: "晴".to_string(),
                 weather_icon_name: "01".to_string(),
                 high_temp: 10,
-                high_temp_diff: TempDiff::new("3"),
+                high_temp_diff: TempDiff::new("3").unwrap(),
                 low_temp: 0,
-                low_temp_diff: TempDiff::new("-5"),
+                low_temp_diff: TempDiff::new("-5").unwrap(),
+                rain_probability: RainProbability::default(),
+                wind: None,
+                wave: None,
             };
 
             assert_eq!(
-                forecast.build_text(),
+                forecast.build_text().unwrap(),
                 "場所: 大雨特別警報,洪水警報,強風注意報,雷注意報 :sunny:: 晴 最高: 10℃[+3] 最低: 0℃[-5] 発表: 日時"
             );
         }
@@ -250,19 +548,20 @@ mod tests {
             let forecast = Forecast {
                 place: "場所".to_string(),
                 date_time: "日時".to_string(),
-                special_warnings: None,
-                warnings: Some(vec!["大雨".to_string()]),
-                alerts: None,
+                advisories: vec![Advisory::new("大雨", Severity::Warning)],
                 weather: "晴".to_string(),
                 weather_icon_name: "01".to_string(),
                 high_temp: 10,
-                high_temp_diff: TempDiff::new("3"),
+                high_temp_diff: TempDiff::new("3").unwrap(),
                 low_temp: 0,
-                low_temp_diff: TempDiff::new("-5"),
+                low_temp_diff: TempDiff::new("-5").unwrap(),
+                rain_probability: RainProbability::default(),
+                wind: None,
+                wave: None,
             };
 
             assert_eq!(
-                forecast.build_text(),
+                forecast.build_text().unwrap(),
                 "場所: 大雨警報 :sunny:: 晴 最高: 10℃[+3] 最低: 0℃[-5] 発表: 日時"
             );
         }
@@ -272,21 +571,239 @@ mod tests {
             let forecast = Forecast {
                 place: "場所".to_string(),
                 date_time: "日時".to_string(),
-                special_warnings: None,
-                warnings: Some(vec!["大雨".to_string()]),
-                alerts: Some(vec!["洪水".to_string(), "雷".to_string()]),
+                advisories: vec![
+                    Advisory::new("大雨", Severity::Warning),
+                    Advisory::new("洪水", Severity::Alert),
+                    Advisory::new("雷", Severity::Alert),
+                ],
                 weather: "晴".to_string(),
                 weather_icon_name: "01".to_string(),
                 high_temp: 10,
-                high_temp_diff: TempDiff::new("3"),
+                high_temp_diff: TempDiff::new("3").unwrap(),
                 low_temp: 0,
-                low_temp_diff: TempDiff::new("-5"),
+                low_temp_diff: TempDiff::new("-5").unwrap(),
+                rain_probability: RainProbability::default(),
+                wind: None,
+                wave: None,
             };
 
             assert_eq!(
-                forecast.build_text(),
+                forecast.build_text().unwrap(),
                 "場所: 大雨警報,洪水注意報,雷注意報 :sunny:: 晴 最高: 10℃[+3] 最低: 0℃[-5] 発表: 日時"
             );
         }
+
+        #[test]
+        fn rain_probability() {
+            let forecast = Forecast {
+                place: "場所".to_string(),
+                date_time: "日時".to_string(),
+                advisories: vec![],
+                weather: "雨".to_string(),
+                weather_icon_name: "15".to_string(),
+                high_temp: 10,
+                high_temp_diff: TempDiff::new("3").unwrap(),
+                low_temp: 0,
+                low_temp_diff: TempDiff::new("-5").unwrap(),
+                rain_probability: RainProbability::new([None, None, Some(60), Some(30)]),
+                wind: None,
+                wave: None,
+            };
+
+            assert_eq!(
+                forecast.build_text_at(9).unwrap(),
+                "場所: 雨 最高: 10℃[+3] 最低: 0℃[-5] ☔60% 発表: 日時"
+            );
+        }
+
+        #[test]
+        fn template() {
+            let forecast = Forecast {
+                place: "場所".to_string(),
+                date_time: "日時".to_string(),
+                advisories: vec![
+                    Advisory::new("洪水", Severity::Alert),
+                    Advisory::new("雷", Severity::Alert),
+                ],
+                weather: "雨".to_string(),
+                weather_icon_name: "15".to_string(),
+                high_temp: 10,
+                high_temp_diff: TempDiff::new("3").unwrap(),
+                low_temp: 0,
+                low_temp_diff: TempDiff::new("-5").unwrap(),
+                rain_probability: RainProbability::new([None, None, Some(60), Some(30)]),
+                wind: None,
+                wave: None,
+            };
+            let template =
+                Template::parse("{weather} {high_temp}/{low_temp}℃{?precip} ☔{precip}%{/precip}{?warnings} {warnings}{/warnings}{?alerts} {alerts}{/alerts}")
+                    .unwrap();
+            let options = TextOptions {
+                template,
+                ..TextOptions::default()
+            };
+
+            assert_eq!(
+                forecast.build_text_with(&options, Some(60)).unwrap(),
+                "雨 10/0℃ ☔60% 洪水注意報,雷注意報"
+            );
+            assert_eq!(
+                forecast.build_text_with(&options, None).unwrap(),
+                "雨 10/0℃ 洪水注意報,雷注意報"
+            );
+        }
+    }
+
+    mod test_max_length {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        fn forecast() -> Forecast {
+            Forecast {
+                place: "練馬区".to_string(),
+                date_time: "30日16:00".to_string(),
+                advisories: vec![
+                    Advisory::new("大雨", Severity::Warning),
+                    Advisory::new("洪水", Severity::Warning),
+                    Advisory::new("強風", Severity::Alert),
+                    Advisory::new("雷", Severity::Alert),
+                    Advisory::new("波浪", Severity::Alert),
+                ],
+                weather: "雨".to_string(),
+                weather_icon_name: "15".to_string(),
+                high_temp: 29,
+                high_temp_diff: TempDiff::new("-4").unwrap(),
+                low_temp: 26,
+                low_temp_diff: TempDiff::new("1").unwrap(),
+                rain_probability: RainProbability::default(),
+                wind: None,
+                wave: None,
+            }
+        }
+
+        fn build(max_length: usize) -> String {
+            forecast()
+                .build_text_with(
+                    &TextOptions {
+                        max_length,
+                        ..TextOptions::default()
+                    },
+                    None,
+                )
+                .unwrap()
+        }
+
+        #[test]
+        fn fits() {
+            assert_eq!(
+                build(100),
+                "練馬区: 大雨警報,洪水警報,強風注意報,雷注意報,波浪注意報 :umbrella:: 雨 最高: 29℃[-4] 最低: 26℃[+1] 発表: 30日16:00"
+            );
+        }
+
+        #[test]
+        fn no_date_time() {
+            assert_eq!(
+                build(81),
+                "練馬区: 大雨警報,洪水警報,強風注意報,雷注意報,波浪注意報 :umbrella:: 雨 最高: 29℃[-4] 最低: 26℃[+1]"
+            );
+        }
+
+        #[test]
+        fn no_diffs() {
+            assert_eq!(
+                build(68),
+                "練馬区: 大雨警報,洪水警報,強風注意報,雷注意報,波浪注意報 :umbrella:: 雨 最高: 29℃ 最低: 26℃"
+            );
+        }
+
+        #[test]
+        fn collapsed_advisories() {
+            assert_eq!(
+                build(60),
+                "練馬区: 警報×2,注意報×3 :umbrella:: 雨 最高: 29℃ 最低: 26℃"
+            );
+        }
+
+        #[test]
+        fn truncated() {
+            // Not `:um…`
+            assert_eq!(build(20), "練馬区: 警報×2,注意報×3…");
+        }
+    }
+
+    #[test]
+    fn test_locale() {
+        let forecast = Forecast {
+            place: "Nerima".to_string(),
+            date_time: "30日16:00".to_string(),
+            advisories: vec![
+                Advisory::new("大雨", Severity::Warning),
+                Advisory::new("洪水", Severity::Alert),
+                Advisory::new("雷", Severity::Alert),
+            ],
+            weather: "雨時々曇".to_string(),
+            weather_icon_name: "10".to_string(),
+            high_temp: 29,
+            high_temp_diff: TempDiff::new("-4").unwrap(),
+            low_temp: 26,
+            low_temp_diff: TempDiff::new("1").unwrap(),
+            rain_probability: RainProbability::default(),
+            wind: None,
+            wave: None,
+        };
+        let options = TextOptions {
+            template: Locale::En.default_template().unwrap(),
+            locale: Locale::En,
+            ..TextOptions::default()
+        };
+
+        assert_eq!(
+            forecast.build_text_with(&options, None).unwrap(),
+            "Nerima: Heavy Rain Warning, Flood Advisory, Thunderstorm Advisory :umbrella:: Rain, occasionally cloudy High: 29℃[-4] Low: 26℃[+1] Issued: 16:00 on day 30"
+        );
+    }
+
+    #[test]
+    fn test_fahrenheit() {
+        let forecast = Forecast {
+            place: "場所".to_string(),
+            date_time: "日時".to_string(),
+            advisories: vec![],
+            weather: "晴".to_string(),
+            weather_icon_name: "01".to_string(),
+            high_temp: 29,
+            high_temp_diff: TempDiff::new("1").unwrap(),
+            low_temp: -18,
+            low_temp_diff: TempDiff::new("-4").unwrap(),
+            rain_probability: RainProbability::default(),
+            wind: None,
+            wave: None,
+        };
+        let options = TextOptions {
+            unit: TempUnit::Fahrenheit,
+            ..TextOptions::default()
+        };
+
+        assert_eq!(
+            forecast.build_text_with(&options, None).unwrap(),
+            "場所: 晴 最高: 84℉[+2] 最低: 0℉[-7] 発表: 日時"
+        );
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("雨 :umbrella: 60%", 20), "雨 :umbrella: 60%");
+        // The limit falls inside the shortcode.
+        assert_eq!(truncate("雨 :umbrella: 60%", 8), "雨…");
+        assert_eq!(truncate("雨 :umbrella: 60%", 3), "雨…");
+        assert_eq!(truncate("雨 :umbrella: 60%", 13), "雨 :umbrella:…");
+        assert_eq!(truncate(":sunny::umbrella:", 10), ":sunny:…");
+        // Not a shortcode, but a number is a word
+        assert_eq!(truncate("最高: 29℃ 最低: 26℃", 6), "最高:…");
+        assert_eq!(truncate("Nerima: Rain High: 84", 12), "Nerima:…");
+        assert_eq!(truncate("Nerima: Rain High: 84", 13), "Nerima: Rain…");
+        assert_eq!(truncate("Nerima: Rain High: 84", 15), "Nerima: Rain…");
+        assert_eq!(truncate("Thunderstorm", 5), "Thun…");
     }
 }
@@ -0,0 +1,81 @@
+const SLOT_HOURS: u32 = 6;
+
+/// 降水確率 of the 00-06, 06-12, 12-18 and 18-24 time slots.
+/// Slots which have already passed are shown as `---` on tenki.jp and kept as `None`.
+#[derive(Debug, Default, PartialEq)]
+pub struct RainProbability {
+    slots: [Option<u8>; 4],
+}
+
+impl RainProbability {
+    pub fn new(slots: [Option<u8>; 4]) -> Self {
+        Self { slots }
+    }
+
+    pub fn from_texts(texts: &[String]) -> Self {
+        let mut slots = [None; 4];
+        for (slot, text) in slots.iter_mut().zip(texts) {
+            *slot = text.trim_end_matches('%').trim().parse::<u8>().ok();
+        }
+        Self { slots }
+    }
+
+    pub fn slots(&self) -> &[Option<u8>; 4] {
+        &self.slots
+    }
+
+    /// Returns the chance of the slot containing `hour`, or of the next slot that has a value.
+    pub fn at(&self, hour: u32) -> Option<u8> {
+        let current = (hour / SLOT_HOURS) as usize;
+        self.slots.iter().skip(current).find_map(|x| *x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_from_texts() {
+        let texts = vec![
+            "---".to_string(),
+            "---".to_string(),
+            "40%".to_string(),
+            "100%".to_string(),
+        ];
+
+        assert_eq!(
+            RainProbability::from_texts(&texts),
+            RainProbability::new([None, None, Some(40), Some(100)])
+        );
+    }
+
+    mod test_at {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn current_slot() {
+            let rain_probability = RainProbability::new([Some(0), Some(10), Some(60), Some(30)]);
+
+            assert_eq!(rain_probability.at(0), Some(0));
+            assert_eq!(rain_probability.at(12), Some(60));
+            assert_eq!(rain_probability.at(23), Some(30));
+        }
+
+        #[test]
+        fn next_slot() {
+            let rain_probability = RainProbability::new([None, None, None, Some(70)]);
+
+            assert_eq!(rain_probability.at(9), Some(70));
+        }
+
+        #[test]
+        fn no_slot() {
+            let rain_probability = RainProbability::default();
+
+            assert_eq!(rain_probability.at(9), None);
+        }
+    }
+}
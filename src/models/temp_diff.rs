@@ -1,5 +1,8 @@
 use std::fmt::{self, Display, Formatter};
 
+use crate::error::{Error, Result};
+use crate::models::temp_unit::TempUnit;
+
 #[derive(Debug)]
 pub struct TempDiff {
     temp_diff: i16,
@@ -15,9 +18,46 @@ impl Display for TempDiff {
 }
 
 impl TempDiff {
-    pub fn new(temp_diff: &str) -> Self {
+    pub fn new(temp_diff: &str) -> Result<Self> {
+        Ok(Self {
+            temp_diff: temp_diff
+                .parse::<i16>()
+                .map_err(|_| Error::ParseNumber(temp_diff.to_string()))?,
+        })
+    }
+
+    pub fn convert(&self, unit: TempUnit) -> Self {
         Self {
-            temp_diff: temp_diff.parse::<i16>().unwrap(),
+            temp_diff: unit.convert_diff(self.temp_diff),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_new() {
+        assert_eq!(TempDiff::new("3").unwrap().to_string(), "+3");
+        assert_eq!(TempDiff::new("0").unwrap().to_string(), "0");
+        assert_eq!(TempDiff::new("-5").unwrap().to_string(), "-5");
+        assert!(matches!(TempDiff::new("---"), Err(Error::ParseNumber(x)) if x == "---"));
+    }
+
+    #[test]
+    fn test_convert() {
+        let temp_diff = TempDiff::new("-4").unwrap();
+
+        assert_eq!(temp_diff.convert(TempUnit::Celsius).to_string(), "-4");
+        assert_eq!(temp_diff.convert(TempUnit::Fahrenheit).to_string(), "-7");
+        assert_eq!(
+            TempDiff::new("1")
+                .unwrap()
+                .convert(TempUnit::Fahrenheit)
+                .to_string(),
+            "+2"
+        );
+    }
+}
@@ -0,0 +1,69 @@
+use std::str::FromStr;
+
+/// tenki.jp shows temperatures in Celsius, and they are converted when it's `Fahrenheit`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum TempUnit {
+    #[default]
+    Celsius,
+    Fahrenheit,
+}
+
+impl FromStr for TempUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "celsius" => Ok(Self::Celsius),
+            "fahrenheit" => Ok(Self::Fahrenheit),
+            x => Err(format!("unknown unit: {x}")),
+        }
+    }
+}
+
+impl TempUnit {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Self::Celsius => "℃",
+            Self::Fahrenheit => "℉",
+        }
+    }
+
+    /// Converts a temperature in Celsius, rounding half away from zero.
+    pub fn convert(&self, celsius: i16) -> i16 {
+        match self {
+            Self::Celsius => celsius,
+            Self::Fahrenheit => (f32::from(celsius) * 1.8 + 32.0).round() as i16,
+        }
+    }
+
+    /// Converts a difference of temperatures in Celsius. The offset of 32 doesn't apply to it.
+    pub fn convert_diff(&self, celsius: i16) -> i16 {
+        match self {
+            Self::Celsius => celsius,
+            Self::Fahrenheit => (f32::from(celsius) * 1.8).round() as i16,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_convert() {
+        assert_eq!(TempUnit::Celsius.convert(29), 29);
+        assert_eq!(TempUnit::Fahrenheit.convert(0), 32);
+        assert_eq!(TempUnit::Fahrenheit.convert(29), 84); // 84.2
+        assert_eq!(TempUnit::Fahrenheit.convert(-18), 0); // -0.4
+        assert_eq!(TempUnit::Fahrenheit.convert(-40), -40);
+    }
+
+    #[test]
+    fn test_convert_diff() {
+        assert_eq!(TempUnit::Fahrenheit.convert_diff(0), 0);
+        assert_eq!(TempUnit::Fahrenheit.convert_diff(1), 2); // 1.8
+        assert_eq!(TempUnit::Fahrenheit.convert_diff(-4), -7); // -7.2
+        assert_eq!(TempUnit::Fahrenheit.convert_diff(5), 9);
+    }
+}
@@ -1,10 +1,18 @@
 use std::path::Path;
 
 use reqwest::StatusCode;
-use scraper::{Html, Selector};
+use scraper::{ElementRef, Html, Selector};
 
-use crate::models::{Forecast, TempDiff};
+use crate::error::{Error, Result};
+use crate::models::{
+    Advisory, DailyForecast, Forecast, RainProbability, Ranking, ResolvedDay, Severity, TempDiff,
+    Wave, WeeklyForecast, Wind,
+};
+use crate::retry::RetryPolicy;
 
+const WIND_LABEL: &str = "風";
+const WAVE_LABEL: &str = "波";
+const WEEK_TABLE: &str = ".forecast-point-week";
 const TRIM_CHARS: [char; 3] = ['[', '+', ']'];
 const USER_AGENT: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/138.0.0.0 Safari/537.36";
 
@@ -15,63 +23,199 @@ pub struct TenkiJpForecast {
 }
 
 impl TenkiJpForecast {
-    pub async fn get(url: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        let client = reqwest::Client::builder().user_agent(USER_AGENT).build()?;
+    pub async fn get(url: &str) -> Result<Self> {
+        Self::get_with_retry(url, &RetryPolicy::default()).await
+    }
+
+    /// Retries with exponential backoff on 5xx and timeouts. Other non-2xx statuses fail immediately.
+    pub async fn get_with_retry(url: &str, retry_policy: &RetryPolicy) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .timeout(retry_policy.timeout)
+            .build()?;
+        retry_policy.run(|| Self::fetch(&client, url)).await
+    }
+
+    async fn fetch(client: &reqwest::Client, url: &str) -> Result<Self> {
         let res = client.get(url).send().await?;
+        let status = res.status();
+        if !status.is_success() {
+            return Err(Error::HttpStatus(status));
+        }
+
         Ok(Self {
-            _status: res.status(),
+            _status: status,
             html: Html::parse_document(&res.text().await?),
         })
     }
 
-    pub fn parse(&self) -> Result<Forecast, Box<dyn std::error::Error>> {
+    pub fn parse(&self) -> Result<Forecast> {
+        self.parse_day(ResolvedDay::Today)
+    }
+
+    pub fn parse_day(&self, day: ResolvedDay) -> Result<Forecast> {
+        let section = day.section_selector();
+        let scoped = |selector: &str| format!("{section} {selector}");
+
         Ok(Forecast {
-            place: self.get_text("h2").split("の天気").collect::<Vec<_>>()[0].to_string(),
+            place: self.get_text("h2")?.split("の天気").collect::<Vec<_>>()[0].to_string(),
             date_time: self
-                .get_text(".date-time")
+                .get_text(".date-time")?
                 .split("発表")
                 .collect::<Vec<_>>()[0]
                 .to_string(),
-            special_warnings: self.get_texts(".special-warn-entry"),
-            warnings: self.get_texts(".warn-entry"),
-            alerts: self.get_texts(".alert-entry"),
-            weather: self.get_text(".weather-telop"),
-            weather_icon_name: self.get_weather_icon_name(".weather-icon > img ", "src"),
-            high_temp: self
-                .get_text("dd.high-temp > .value")
-                .parse::<i16>()
-                .unwrap(),
+            advisories: self.get_advisories(),
+            weather: self.get_text(&scoped(".weather-telop"))?,
+            weather_icon_name: self.get_weather_icon_name(&scoped(".weather-icon > img"), "src")?,
+            high_temp: Self::parse_temp(&self.get_text(&scoped("dd.high-temp > .value"))?)?,
             high_temp_diff: TempDiff::new(
                 &self
-                    .get_text("dd.high-temp.tempdiff")
+                    .get_text(&scoped("dd.high-temp.tempdiff"))?
                     .replace(TRIM_CHARS.as_ref(), ""),
-            ),
-            low_temp: self
-                .get_text("dd.low-temp > .value")
-                .parse::<i16>()
-                .unwrap(),
+            )?,
+            low_temp: Self::parse_temp(&self.get_text(&scoped("dd.low-temp > .value"))?)?,
             low_temp_diff: TempDiff::new(
                 &self
-                    .get_text("dd.low-temp.tempdiff")
+                    .get_text(&scoped("dd.low-temp.tempdiff"))?
                     .replace(TRIM_CHARS.as_ref(), ""),
+            )?,
+            rain_probability: RainProbability::from_texts(
+                &self
+                    .get_texts(&scoped(".rain-probability > td"))
+                    .unwrap_or_default(),
             ),
+            wind: self
+                .get_row_text(&scoped(".wind-wave"), WIND_LABEL)
+                .map(|x| Wind::new(&x)),
+            wave: self
+                .get_row_text(&scoped(".wind-wave"), WAVE_LABEL)
+                .map(|x| Wave::new(&x)),
         })
     }
 
-    fn get_text(&self, selector: &str) -> String {
+    /// Parses the 10-day table which is embedded in the forecast page.
+    /// A column is a day, so each day is parsed from its own cells and a missing one is left empty.
+    pub fn parse_weekly(&self) -> Result<WeeklyForecast> {
+        let tr = Selector::parse(&format!("{WEEK_TABLE} tr")).unwrap();
+        let td = Selector::parse("td").unwrap();
+        let rows = self
+            .html
+            .select(&tr)
+            .map(|row| row.select(&td).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let row = |selector: &str| {
+            let selector = Selector::parse(selector).unwrap();
+            rows.iter()
+                .find(|cells| cells.iter().any(|x| x.select(&selector).next().is_some()))
+                .cloned()
+                .unwrap_or_default()
+        };
+        let dates = row(".date-box");
+        let weathers = row("img");
+        let temps = row(".high-temp");
+        let rain_probabilities = row(".precip");
+        if dates.is_empty() {
+            return Err(Error::MissingSelector(format!("{WEEK_TABLE} .date-box")));
+        }
+
+        let text = |cells: &[ElementRef], i: usize, selector: &str| {
+            let selector = Selector::parse(selector).unwrap();
+            cells
+                .get(i)
+                .and_then(|x| x.select(&selector).next())
+                .map(|x| x.text().collect::<String>().trim().to_string())
+                .unwrap_or_default()
+        };
+        let img = Selector::parse("img").unwrap();
+        let days = (0..dates.len())
+            .map(|i| {
+                let weather_icon_name = match weathers
+                    .get(i)
+                    .and_then(|x| x.select(&img).next())
+                    .and_then(|x| x.value().attr("src"))
+                {
+                    Some(x) => Self::get_file_stem(x)?,
+                    None => String::new(),
+                };
+                Ok(DailyForecast {
+                    date: text(&dates, i, ".date-box"),
+                    weekday: text(&dates, i, ".youbi-box > span"),
+                    weather: text(&weathers, i, "p"),
+                    weather_icon_name,
+                    high_temp: text(&temps, i, ".high-temp").parse::<i16>().ok(),
+                    low_temp: text(&temps, i, ".low-temp").parse::<i16>().ok(),
+                    rain_probability: text(&rain_probabilities, i, ".precip")
+                        .trim_end_matches('%')
+                        .parse::<u8>()
+                        .ok(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(WeeklyForecast {
+            place: self
+                .get_text(&format!("{WEEK_TABLE}-wrap h3"))?
+                .split("の10日間天気")
+                .collect::<Vec<_>>()[0]
+                .to_string(),
+            days,
+        })
+    }
+
+    fn get_row_text(&self, selector: &str, label: &str) -> Option<String> {
         let selector = Selector::parse(selector).unwrap();
-        match self.html.select(&selector).next() {
-            Some(x) => x
+        let th = Selector::parse("th").unwrap();
+        let td = Selector::parse("td").unwrap();
+
+        self.html
+            .select(&selector)
+            .find(|row| {
+                row.select(&th)
+                    .next()
+                    .is_some_and(|x| x.text().collect::<String>().trim() == label)
+            })
+            .and_then(|row| row.select(&td).next())
+            .map(|x| x.text().collect::<String>().trim().into())
+    }
+
+    fn parse_temp(text: &str) -> Result<i16> {
+        text.parse::<i16>()
+            .map_err(|_| Error::ParseNumber(text.to_string()))
+    }
+
+    fn get_text(&self, selector: &str) -> Result<String> {
+        let parsed_selector = Selector::parse(selector).unwrap();
+        match self.html.select(&parsed_selector).next() {
+            Some(x) => Ok(x
                 .text()
                 .collect::<Vec<_>>()
                 .into_iter()
                 .collect::<String>()
                 .trim()
-                .into(),
-            None => "".to_string(),
+                .into()),
+            None => Err(Error::MissingSelector(selector.to_string())),
         }
     }
 
+    fn get_advisories(&self) -> Vec<Advisory> {
+        let levels = [
+            (".special-warn-entry", Severity::SpecialWarning),
+            (".warn-entry", Severity::Warning),
+            (".alert-entry", Severity::Alert),
+        ];
+        let advisories = levels
+            .into_iter()
+            .flat_map(|(selector, level)| {
+                self.get_texts(selector)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(move |x| Advisory::new(&x, level))
+            })
+            .collect();
+
+        Ranking::default().apply(advisories)
+    }
+
     fn get_texts(&self, selector: &str) -> Option<Vec<String>> {
         let selector = Selector::parse(selector).unwrap();
         let texts = self
@@ -93,8 +237,10 @@ impl TenkiJpForecast {
         }
     }
 
-    fn get_weather_icon_name(&self, selector: &str, attr: &str) -> String {
-        let weather_icon_path = self.get_attr(selector, attr).unwrap_or_default();
+    fn get_weather_icon_name(&self, selector: &str, attr: &str) -> Result<String> {
+        let weather_icon_path = self
+            .get_attr(selector, attr)
+            .ok_or_else(|| Error::MissingSelector(format!("{selector}[{attr}]")))?;
         Self::get_file_stem(&weather_icon_path)
     }
 
@@ -104,19 +250,16 @@ impl TenkiJpForecast {
         self.html
             .select(&selector)
             .next()
-            .unwrap()
-            .value()
-            .attr(attr)
+            .and_then(|x| x.value().attr(attr))
             .map(|x| x.into())
     }
 
-    fn get_file_stem(path: &str) -> String {
+    fn get_file_stem(path: &str) -> Result<String> {
         Path::new(path)
             .file_stem()
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .to_string()
+            .and_then(|x| x.to_str())
+            .map(|x| x.to_string())
+            .ok_or_else(|| Error::InvalidIconPath(path.to_string()))
     }
 }
 
@@ -125,6 +268,141 @@ mod tests {
     use super::*;
     use pretty_assertions::assert_eq;
 
+    #[test]
+    fn test_parse() {
+        let tenki_jp_forecast = TenkiJpForecast {
+            _status: Default::default(),
+            html: Html::parse_document(include_str!("../../tests/multiple_alert.html")),
+        };
+        let forecast = tenki_jp_forecast.parse().unwrap();
+
+        assert_eq!(
+            forecast.rain_probability,
+            RainProbability::new([None, None, None, Some(40)])
+        );
+        assert_eq!(forecast.wind, Some(Wind::new("東の風後北東の風")));
+        assert_eq!(forecast.wave, None);
+    }
+
+    #[test]
+    fn test_parse_changed_layout() {
+        let tenki_jp_forecast = TenkiJpForecast {
+            _status: Default::default(),
+            html: Html::parse_document("<html><h2>場所の天気</h2></html>"),
+        };
+
+        assert!(matches!(
+            tenki_jp_forecast.parse(),
+            Err(Error::MissingSelector(x)) if x == ".date-time"
+        ));
+    }
+
+    #[test]
+    fn test_parse_day() {
+        let tenki_jp_forecast = TenkiJpForecast {
+            _status: Default::default(),
+            html: Html::parse_document(include_str!("../../tests/special_warning.html")),
+        };
+        let forecast = tenki_jp_forecast.parse_day(ResolvedDay::Tomorrow).unwrap();
+
+        assert_eq!(forecast.place, "波佐見町");
+        assert_eq!(forecast.weather, "曇");
+        assert_eq!(forecast.weather_icon_name, "08");
+        assert_eq!(forecast.high_temp, 29);
+        assert_eq!(forecast.high_temp_diff.to_string(), "+2");
+        assert_eq!(forecast.low_temp, 24);
+        assert_eq!(
+            forecast.rain_probability,
+            RainProbability::new([Some(30), Some(10), Some(10), Some(0)])
+        );
+        assert_eq!(forecast.wind, Some(Wind::new("北の風後北東の風")));
+    }
+
+    mod test_get_row_text {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn found() {
+            let tenki_jp_forecast = TenkiJpForecast {
+                _status: Default::default(),
+                html: Html::parse_document(include_str!("../../tests/multiple_warn_alert.html")),
+            };
+
+            assert_eq!(
+                tenki_jp_forecast.get_row_text(".wind-wave", WIND_LABEL),
+                Some("南東の風後南の風".to_string())
+            );
+            assert_eq!(
+                tenki_jp_forecast.get_row_text(".wind-wave", WAVE_LABEL),
+                Some("3mうねりを伴う".to_string())
+            );
+        }
+
+        #[test]
+        fn not_found() {
+            let tenki_jp_forecast = TenkiJpForecast {
+                _status: Default::default(),
+                html: Html::parse_document("<html></html>"),
+            };
+
+            assert_eq!(
+                tenki_jp_forecast.get_row_text(".wind-wave", WAVE_LABEL),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_weekly() {
+        let tenki_jp_forecast = TenkiJpForecast {
+            _status: Default::default(),
+            html: Html::parse_document(include_str!("../../tests/special_warning.html")),
+        };
+        let weekly_forecast = tenki_jp_forecast.parse_weekly().unwrap();
+
+        assert_eq!(weekly_forecast.place, "波佐見町");
+        assert_eq!(weekly_forecast.days.len(), 9);
+        assert_eq!(
+            weekly_forecast.days[0],
+            DailyForecast {
+                date: "08月16日".to_string(),
+                weekday: "月".to_string(),
+                weather: "雨時々曇".to_string(),
+                weather_icon_name: "17".to_string(),
+                high_temp: Some(26),
+                low_temp: Some(23),
+                rain_probability: Some(70),
+            }
+        );
+        assert_eq!(weekly_forecast.days[5].weekday, "土");
+        assert_eq!(weekly_forecast.days[8].date, "08月24日");
+    }
+
+    #[test]
+    fn test_parse_weekly_missing_cell() {
+        // The 最高気温 of 08月16日 and the 降水確率 of 08月17日 are missing.
+        let html = include_str!("../../tests/special_warning.html")
+            .replacen(r#"<p class="high-temp">26</p>"#, "", 1)
+            .replacen(
+                r#"<p class="precip">80<span class="unit">%</span></p>"#,
+                "",
+                1,
+            );
+        let tenki_jp_forecast = TenkiJpForecast {
+            _status: Default::default(),
+            html: Html::parse_document(&html),
+        };
+        let weekly_forecast = tenki_jp_forecast.parse_weekly().unwrap();
+
+        assert_eq!(weekly_forecast.days.len(), 9);
+        assert_eq!(weekly_forecast.days[0].high_temp, None);
+        assert_eq!(weekly_forecast.days[0].low_temp, Some(23));
+        assert_eq!(weekly_forecast.days[1].rain_probability, None);
+        assert_eq!(weekly_forecast.days[2].rain_probability, Some(100));
+        assert_eq!(weekly_forecast.days[8].rain_probability, Some(70));
+    }
+
     #[test]
     fn test_get_text() {
         let tenki_jp_forecast = TenkiJpForecast {
@@ -134,7 +412,11 @@ mod tests {
             ),
         };
 
-        assert_eq!(tenki_jp_forecast.get_text("h2"), "h2要素");
+        assert_eq!(tenki_jp_forecast.get_text("h2").unwrap(), "h2要素");
+        assert!(matches!(
+            tenki_jp_forecast.get_text("h4"),
+            Err(Error::MissingSelector(x)) if x == "h4"
+        ));
     }
 
     mod test_get_texts {
@@ -189,7 +471,7 @@ mod tests {
         #[test]
         fn daytime() {
             let str = "https://static.tenki.jp/images/icon/forecast-days-weather/12.png";
-            let result = TenkiJpForecast::get_file_stem(str);
+            let result = TenkiJpForecast::get_file_stem(str).unwrap();
 
             assert_eq!(result, "12".to_string());
         }
@@ -197,7 +479,7 @@ mod tests {
         #[test]
         fn night() {
             let str = "https://static.tenki.jp/images/icon/forecast-days-weather/12_n.png";
-            let result = TenkiJpForecast::get_file_stem(str);
+            let result = TenkiJpForecast::get_file_stem(str).unwrap();
 
             assert_eq!(result, "12_n".to_string());
         }
@@ -0,0 +1,85 @@
+use crate::models::temp_unit::TempUnit;
+
+#[derive(Debug, PartialEq)]
+pub struct DailyForecast {
+    pub date: String,    // e.g. 08月16日
+    pub weekday: String, // e.g. 月
+    pub weather: String,
+    pub weather_icon_name: String,
+    pub high_temp: Option<i16>,
+    pub low_temp: Option<i16>,
+    pub rain_probability: Option<u8>, // 降水確率
+}
+
+#[derive(Debug, PartialEq)]
+pub struct WeeklyForecast {
+    pub place: String,
+    pub days: Vec<DailyForecast>,
+}
+
+impl WeeklyForecast {
+    pub fn build_summary(&self, unit: TempUnit) -> String {
+        let days = self
+            .days
+            .iter()
+            .map(|day| {
+                format!(
+                    "{}({}) {} {}/{}",
+                    day.date,
+                    day.weekday,
+                    day.weather,
+                    Self::temp_text(day.high_temp, unit),
+                    Self::temp_text(day.low_temp, unit)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        format!("{}: {}", self.place, days)
+    }
+
+    fn temp_text(temp: Option<i16>, unit: TempUnit) -> String {
+        match temp {
+            Some(x) => format!("{}{}", unit.convert(x), unit.symbol()),
+            None => "---".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_build_summary() {
+        let weekly_forecast = WeeklyForecast {
+            place: "場所".to_string(),
+            days: vec![
+                DailyForecast {
+                    date: "08月16日".to_string(),
+                    weekday: "月".to_string(),
+                    weather: "雨".to_string(),
+                    weather_icon_name: "15".to_string(),
+                    high_temp: Some(26),
+                    low_temp: Some(23),
+                    rain_probability: Some(70),
+                },
+                DailyForecast {
+                    date: "08月17日".to_string(),
+                    weekday: "火".to_string(),
+                    weather: "晴".to_string(),
+                    weather_icon_name: "01".to_string(),
+                    high_temp: Some(30),
+                    low_temp: None,
+                    rain_probability: None,
+                },
+            ],
+        };
+
+        assert_eq!(
+            weekly_forecast.build_summary(TempUnit::Celsius),
+            "場所: 08月16日(月) 雨 26℃/23℃, 08月17日(火) 晴 30℃/---"
+        );
+    }
+}
@@ -0,0 +1,138 @@
+const WIND_SUFFIX: &str = "の風";
+
+#[derive(Debug, PartialEq, PartialOrd)]
+pub enum WindStrength {
+    Normal,
+    SlightlyStrong, // やや強く
+    Strong,         // 強く
+    VeryStrong,     // 非常に強く
+}
+
+impl WindStrength {
+    fn from_text(text: &str) -> Self {
+        if text.contains("非常に強く") {
+            return Self::VeryStrong;
+        }
+
+        // "強く" is also a part of "やや強く"
+        if text.replace("やや強く", "").contains("強く") {
+            return Self::Strong;
+        }
+
+        if text.contains("やや強く") {
+            return Self::SlightlyStrong;
+        }
+
+        Self::Normal
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Wind {
+    pub text: String,
+    pub direction: Option<String>,
+    pub strength: WindStrength,
+}
+
+impl Wind {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            direction: text
+                .split_once(WIND_SUFFIX)
+                .map(|(direction, _)| direction.to_string()),
+            strength: WindStrength::from_text(text),
+        }
+    }
+
+    pub fn is_strong(&self) -> bool {
+        self.strength >= WindStrength::Strong
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Wave {
+    pub text: String,
+    pub height: Option<f32>, // meters, the highest one when it changes during the day
+}
+
+impl Wave {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            height: Self::parse_height(text),
+        }
+    }
+
+    fn parse_height(text: &str) -> Option<f32> {
+        let mut heights = vec![];
+        let mut number = String::new();
+        for c in text.chars() {
+            match c {
+                '0'..='9' | '.' => number.push(c),
+                'm' => {
+                    if let Ok(height) = number.parse::<f32>() {
+                        heights.push(height);
+                    }
+                    number.clear();
+                }
+                _ => number.clear(),
+            }
+        }
+
+        heights.into_iter().reduce(f32::max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    mod test_wind {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn normal() {
+            let wind = Wind::new("南西の風後北の風");
+
+            assert_eq!(wind.direction, Some("南西".to_string()));
+            assert_eq!(wind.strength, WindStrength::Normal);
+            assert!(!wind.is_strong());
+        }
+
+        #[test]
+        fn slightly_strong() {
+            let wind = Wind::new("北の風やや強く");
+
+            assert_eq!(wind.direction, Some("北".to_string()));
+            assert_eq!(wind.strength, WindStrength::SlightlyStrong);
+            assert!(!wind.is_strong());
+        }
+
+        #[test]
+        fn strong() {
+            let wind = Wind::new("北西の風やや強く後北西の風強く");
+
+            assert_eq!(wind.direction, Some("北西".to_string()));
+            assert_eq!(wind.strength, WindStrength::Strong);
+            assert!(wind.is_strong());
+        }
+
+        #[test]
+        fn very_strong() {
+            let wind = Wind::new("南の風非常に強く");
+
+            assert_eq!(wind.strength, WindStrength::VeryStrong);
+            assert!(wind.is_strong());
+        }
+    }
+
+    #[test]
+    fn test_wave() {
+        assert_eq!(Wave::new("3m後2mうねりを伴う").height, Some(3.0));
+        assert_eq!(Wave::new("0.5m後1m").height, Some(1.0));
+        assert_eq!(Wave::new("---").height, None);
+    }
+}
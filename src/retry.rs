@@ -0,0 +1,130 @@
+use std::future::Future;
+use std::time::Duration;
+
+use log::warn;
+
+use crate::error::Result;
+
+const DEFAULT_MAX_RETRIES: u32 = 3;
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff: Duration, // doubled on every retry
+    pub timeout: Duration,         // of each request
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: DEFAULT_MAX_RETRIES,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff * 2u32.saturating_pow(attempt)
+    }
+
+    /// Runs `f` until it succeeds, fails with a non-retryable error or runs out of retries.
+    pub async fn run<T, F, Fut>(&self, mut f: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match f().await {
+                Err(e) if attempt < self.max_retries && e.is_retryable() => {
+                    let backoff = e.retry_after().unwrap_or_else(|| self.backoff(attempt));
+                    warn!("{e}, retrying in {backoff:?}");
+                    tokio::time::delay_for(backoff).await;
+                    attempt += 1;
+                }
+                x => return x,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use pretty_assertions::assert_eq;
+    use reqwest::StatusCode;
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy::default();
+
+        assert_eq!(policy.backoff(0), Duration::from_secs(1));
+        assert_eq!(policy.backoff(1), Duration::from_secs(2));
+        assert_eq!(policy.backoff(3), Duration::from_secs(8));
+    }
+
+    mod test_run {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        fn policy() -> RetryPolicy {
+            RetryPolicy {
+                max_retries: 2,
+                initial_backoff: Duration::from_millis(0),
+                timeout: Duration::from_secs(1),
+            }
+        }
+
+        #[tokio::test]
+        async fn server_error() {
+            let mut attempts = 0;
+            let result: Result<()> = policy()
+                .run(|| {
+                    attempts += 1;
+                    async { Err(Error::HttpStatus(StatusCode::SERVICE_UNAVAILABLE)) }
+                })
+                .await;
+
+            assert!(result.is_err());
+            assert_eq!(attempts, 3);
+        }
+
+        #[tokio::test]
+        async fn client_error() {
+            let mut attempts = 0;
+            let result: Result<()> = policy()
+                .run(|| {
+                    attempts += 1;
+                    async { Err(Error::HttpStatus(StatusCode::NOT_FOUND)) }
+                })
+                .await;
+
+            assert!(result.is_err());
+            assert_eq!(attempts, 1);
+        }
+
+        #[tokio::test]
+        async fn recovered() {
+            let mut attempts = 0;
+            let result = policy()
+                .run(|| {
+                    attempts += 1;
+                    let attempt = attempts;
+                    async move {
+                        match attempt {
+                            1 => Err(Error::HttpStatus(StatusCode::BAD_GATEWAY)),
+                            _ => Ok(attempt),
+                        }
+                    }
+                })
+                .await;
+
+            assert_eq!(result.unwrap(), 2);
+        }
+    }
+}
@@ -0,0 +1,651 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+
+use chrono::{DateTime, Utc};
+use futures::future::join_all;
+use log::{info, warn};
+
+use crate::blocks;
+use crate::config::{Config, User};
+use crate::emoji::{EmojiMaps, UnknownEmoji};
+use crate::error::{Error, Result};
+use crate::models::{
+    AdvisoryChanges, AlertOnly, Forecast, NoAlertAction, ResolvedDay, TenkiJpForecast, TextOptions,
+};
+use crate::retry::RetryPolicy;
+use crate::schedule::{jst, jst_hour};
+use crate::slack::{Destination, Profile, SlackRequest};
+use crate::state::State;
+
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    Updated {
+        emoji: String,
+        text: String,
+        expiration: i64,
+    },
+    Unchanged,
+    KeptManualStatus,
+    NoAlert, // kept the status, because there was no advisory of the level
+    DryRun {
+        emoji: String,
+        text: String,
+    },
+    Skipped, // the forecast page couldn't be fetched or parsed
+}
+
+impl From<Profile> for Outcome {
+    fn from(profile: Profile) -> Self {
+        Self::Updated {
+            emoji: profile.status_emoji,
+            text: profile.status_text,
+            expiration: profile.status_expiration,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Report {
+    pub name: String,
+    pub result: Result<Outcome>,
+}
+
+/// The advisories of a forecast page which were posted to the channel.
+#[derive(Debug)]
+pub struct Notification {
+    pub place: String,
+    pub result: Result<AdvisoryChanges>,
+}
+
+#[derive(Debug)]
+pub struct Summary {
+    pub date_times: Vec<String>, // 発表 of the fetched pages
+    pub unknown_emoji: UnknownEmoji,
+    pub failed_urls: Vec<(String, Error)>,
+    pub notifications: Vec<Notification>,
+    pub posts: Vec<(String, Result<()>)>, // the forecast cards of the places
+    pub reports: Vec<Report>,
+}
+
+impl Summary {
+    pub fn is_success(&self) -> bool {
+        self.failed_urls.is_empty()
+            && self.notifications.iter().all(|x| x.result.is_ok())
+            && self.posts.iter().all(|(_, x)| x.is_ok())
+            && self.reports.iter().all(|x| x.result.is_ok())
+    }
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (url, e) in &self.failed_urls {
+            writeln!(f, "{url}: failed: {e}")?;
+        }
+
+        for notification in &self.notifications {
+            let place = &notification.place;
+            match &notification.result {
+                Ok(x) => writeln!(
+                    f,
+                    "{place}: posted {} issued, {} lifted, {} upgraded and {} downgraded advisories",
+                    x.issued.len(),
+                    x.lifted.len(),
+                    x.upgraded.len(),
+                    x.downgraded.len()
+                )?,
+                Err(e) => writeln!(f, "{place}: failed to post: {e}")?,
+            }
+        }
+
+        for (place, result) in &self.posts {
+            match result {
+                Ok(()) => writeln!(f, "{place}: posted the forecast")?,
+                Err(e) => writeln!(f, "{place}: failed to post the forecast: {e}")?,
+            }
+        }
+
+        for report in &self.reports {
+            let name = &report.name;
+            match &report.result {
+                Ok(Outcome::Updated { emoji, text, .. }) if emoji.is_empty() && text.is_empty() => {
+                    writeln!(f, "{name}: cleared")?
+                }
+                Ok(Outcome::Updated { emoji, text, .. }) => {
+                    writeln!(f, "{name}: updated {emoji:?}, {text:?}")?
+                }
+                Ok(Outcome::Unchanged) => writeln!(f, "{name}: unchanged")?,
+                Ok(Outcome::KeptManualStatus) => {
+                    writeln!(f, "{name}: kept the status set by hand")?
+                }
+                Ok(Outcome::NoAlert) => writeln!(f, "{name}: no alert, kept the status")?,
+                Ok(Outcome::DryRun { emoji, text }) => writeln!(f, "{name}: {emoji:?}, {text:?}")?,
+                Ok(Outcome::Skipped) => writeln!(f, "{name}: skipped")?,
+                Err(e) => writeln!(f, "{name}: failed: {e}")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Fetches every distinct forecast page once, and updates the statuses of all users concurrently.
+pub struct Runner {
+    pub config: Config,
+    pub force: bool,
+    pub dry_run: bool,
+}
+
+impl Runner {
+    pub async fn run(&self) -> Result<Summary> {
+        let users = self.config.users()?;
+        let retry_policy = self.config.retry_policy();
+        let started_at = Utc::now();
+        let hour = jst_hour(started_at);
+        let day = self.config.day()?.resolve(hour);
+
+        let mut urls = users.iter().map(|x| x.url.as_str()).collect::<Vec<_>>();
+        urls.sort_unstable();
+        urls.dedup();
+        let pages = join_all(urls.iter().map(|url| Self::fetch(url, &retry_policy, day))).await;
+
+        let ranking = self.config.ranking()?;
+        let mut forecasts = HashMap::new();
+        let mut date_times = vec![];
+        let mut failed_urls = vec![];
+        for (url, page) in urls.into_iter().zip(pages) {
+            match page {
+                Ok(mut forecast) => {
+                    forecast.advisories = ranking.apply(forecast.advisories);
+                    info!("{forecast:?}");
+                    date_times.push(forecast.date_time.clone());
+                    forecasts.insert(url, forecast);
+                }
+                Err(e) => failed_urls.push((url.to_string(), e)),
+            }
+        }
+
+        // The time slot of 降水確率. Tomorrow's forecast shows the first one.
+        let rain_hour = match (self.config.rain(), day) {
+            (true, ResolvedDay::Tomorrow) => Some(0),
+            (true, _) => Some(hour),
+            (false, _) => None,
+        };
+        let options = TextOptions {
+            max_length: self.config.max_length(),
+            locale: self.config.locale()?,
+            unit: self.config.unit()?,
+            emoji_maps: self.config.emoji_maps()?,
+            ..TextOptions::default()
+        };
+
+        let mut unknown_emoji = UnknownEmoji::default();
+        for forecast in forecasts.values() {
+            unknown_emoji.extend(forecast.unknown_emoji(&options.emoji_maps));
+        }
+
+        let state_path = self.config.state_path().filter(|_| !self.dry_run);
+        let mut state = match &state_path {
+            Some(path) => State::load(path)?,
+            None => State::default(),
+        };
+        let last_unknown_emoji = state.unknown_emoji.clone();
+        state.unknown_emoji.retain_unknown(&options.emoji_maps);
+        state.unknown_emoji.extend(unknown_emoji.clone());
+        let last_advisories = state.advisories.clone();
+        // The changes can't be known without the state, e.g. in a dry run.
+        let notifications = match state_path.is_some() {
+            true => {
+                self.notify(&forecasts, &mut state, &options, &retry_policy)
+                    .await?
+            }
+            false => {
+                if !self.dry_run && self.config.destination()?.is_some() {
+                    warn!("no state file, so the advisories aren't posted");
+                }
+                vec![]
+            }
+        };
+        let last_cards = state.cards.clone();
+        let posts = match self.config.post_to()? {
+            Some(destination) if !self.dry_run => {
+                self.post_forecasts(
+                    &destination,
+                    &forecasts,
+                    &mut state,
+                    &options,
+                    &retry_policy,
+                    started_at,
+                )
+                .await?
+            }
+            _ => vec![],
+        };
+        let users = match self.config.post_only()? {
+            true => &[][..],
+            false => &users[..],
+        };
+        let alert_only = self.config.alert_only()?;
+        let now = Utc::now().timestamp();
+
+        let results = join_all(users.iter().map(|user| async {
+            let Some(forecast) = forecasts.get(user.url.as_str()) else {
+                return Ok(Outcome::Skipped);
+            };
+            match Self::build_status(user, forecast, &options, alert_only, rain_hour)? {
+                Some((emoji, text)) => {
+                    self.update(user, emoji, text, &state, &options.emoji_maps, now)
+                        .await
+                }
+                None => Ok(Outcome::NoAlert),
+            }
+        }))
+        .await;
+
+        let mut is_updated = false;
+        for (user, result) in users.iter().zip(&results) {
+            if let Ok(Outcome::Updated {
+                emoji,
+                text,
+                expiration,
+            }) = result
+            {
+                state.update(&user.name, emoji, text, now, *expiration);
+                is_updated = true;
+            }
+        }
+        let is_changed = is_updated
+            || state.unknown_emoji != last_unknown_emoji
+            || state.advisories != last_advisories
+            || state.cards != last_cards;
+        if let (Some(path), true) = (&state_path, is_changed) {
+            state.save(path)?;
+        }
+
+        let reports = users
+            .iter()
+            .zip(results)
+            .map(|(user, result)| Report {
+                name: user.name.clone(),
+                result,
+            })
+            .collect();
+        Ok(Summary {
+            date_times,
+            unknown_emoji,
+            failed_urls,
+            notifications,
+            posts,
+            reports,
+        })
+    }
+
+    /// Posts the card of a forecast once a morning, so that neither the daemon mode nor a frequent cron job posts it
+    /// on every run or 発表, and saves the date of the posted ones to `state`.
+    async fn post_forecasts(
+        &self,
+        destination: &Destination,
+        forecasts: &HashMap<&str, Forecast>,
+        state: &mut State,
+        options: &TextOptions,
+        retry_policy: &RetryPolicy,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<(String, Result<()>)>> {
+        let post_hour = self.config.post_hour()?;
+        let now = now.with_timezone(&jst());
+        let forecasts = forecasts
+            .iter()
+            .filter(|(url, _)| self.force || state.is_card_due(url, now, post_hour))
+            .collect::<Vec<_>>();
+        let results = join_all(forecasts.iter().map(|(url, forecast)| async move {
+            match blocks::forecast(forecast, url, options) {
+                Ok(message) => destination.post(&message, retry_policy).await,
+                Err(e) => Err(e),
+            }
+        }))
+        .await;
+
+        let date = now.date_naive().to_string();
+        Ok(forecasts
+            .into_iter()
+            .zip(results)
+            .map(|((url, forecast), result)| {
+                if result.is_ok() {
+                    state.cards.insert(url.to_string(), date.clone());
+                }
+                (forecast.place.clone(), result)
+            })
+            .collect())
+    }
+
+    /// Posts the advisories which were issued, lifted or changed their level since the last run, and saves the current ones to `state`.
+    /// A page seen for the first time is only saved, so that the advisories in effect aren't posted as new ones.
+    async fn notify(
+        &self,
+        forecasts: &HashMap<&str, Forecast>,
+        state: &mut State,
+        options: &TextOptions,
+        retry_policy: &RetryPolicy,
+    ) -> Result<Vec<Notification>> {
+        let Some(destination) = self.config.destination()? else {
+            return Ok(vec![]);
+        };
+        let min_level = self.config.notify_level()?;
+
+        let mut notifications = vec![];
+        for (url, forecast) in forecasts {
+            let Some(previous) = state
+                .advisories
+                .insert(url.to_string(), forecast.advisories.clone())
+            else {
+                continue;
+            };
+
+            let changes = AdvisoryChanges::diff(&previous, &forecast.advisories, min_level);
+            if changes.is_empty() {
+                continue;
+            }
+
+            let message = blocks::advisory_changes(
+                forecast,
+                url,
+                &changes,
+                options.locale,
+                &options.emoji_maps,
+            );
+            let result = destination.post(&message, retry_policy).await;
+            if result.is_err() {
+                // Posted again on the next run.
+                state.advisories.insert(url.to_string(), previous);
+            }
+            notifications.push(Notification {
+                place: forecast.place.clone(),
+                result: result.map(|_| changes),
+            });
+        }
+        Ok(notifications)
+    }
+
+    async fn fetch(url: &str, retry_policy: &RetryPolicy, day: ResolvedDay) -> Result<Forecast> {
+        TenkiJpForecast::get_with_retry(url, retry_policy)
+            .await?
+            .parse_day(day)
+    }
+
+    /// The emoji and the text of the status, which are empty to clear it. `None` keeps the status
+    /// in the alert-only mode.
+    fn build_status(
+        user: &User,
+        forecast: &Forecast,
+        options: &TextOptions,
+        alert_only: Option<AlertOnly>,
+        rain_hour: Option<u32>,
+    ) -> Result<Option<(String, String)>> {
+        match alert_only {
+            Some(x) if !x.is_alerting(forecast) => match x.action {
+                NoAlertAction::Clear => Ok(Some(("".to_string(), "".to_string()))),
+                NoAlertAction::Keep => Ok(None),
+            },
+            _ => {
+                let options = TextOptions {
+                    template: user.template.clone(),
+                    ..options.clone()
+                };
+                let rain_probability = rain_hour.and_then(|x| forecast.rain_probability.at(x));
+                Ok(Some((
+                    forecast.build_emoji_with(&options.emoji_maps),
+                    forecast.build_text_with(&options, rain_probability)?,
+                )))
+            }
+        }
+    }
+
+    async fn update(
+        &self,
+        user: &User,
+        emoji: String,
+        text: String,
+        state: &State,
+        emoji_maps: &EmojiMaps,
+        now: i64,
+    ) -> Result<Outcome> {
+        if self.dry_run {
+            return Ok(Outcome::DryRun { emoji, text });
+        }
+
+        if !self.force && state.is_unchanged(&user.name, &emoji, &text, now) {
+            return Ok(Outcome::Unchanged);
+        }
+
+        let slack_request = SlackRequest::with_retry(user.token()?, &self.config.retry_policy());
+        if self.config.keep_manual_status() {
+            let profile = slack_request.get_profile().await?;
+            let last_emoji = state.statuses.get(&user.name).map(|x| x.emoji.as_str());
+            if !profile.is_overwritable(emoji_maps, last_emoji) {
+                info!("{}: keep the status set by hand: {profile:?}", user.name);
+                return Ok(Outcome::KeptManualStatus);
+            }
+        }
+
+        // A cleared status doesn't need to expire.
+        let expire_after = self.config.expire_after()?.filter(|_| !emoji.is_empty());
+        let profile = Profile::new(&emoji, &text, expire_after);
+        let res = slack_request.update_status(&profile).await?;
+        if let Some(warning) = &res.warning {
+            warn!("{}: Slack API warning: {warning}", user.name);
+        }
+        info!("{}: {res:?}", user.name);
+
+        // The expiration of the sent profile, since the response doesn't always have the profile.
+        Ok(profile.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Advisory, Day, RainProbability, Severity, TempDiff};
+    use chrono::TimeZone;
+    use pretty_assertions::assert_eq;
+    use reqwest::StatusCode;
+    use std::time::Duration;
+
+    fn forecast(advisories: Vec<Advisory>) -> Forecast {
+        Forecast {
+            place: "練馬区".to_string(),
+            date_time: "30日16:00".to_string(),
+            advisories,
+            weather: "晴".to_string(),
+            weather_icon_name: "01".to_string(),
+            high_temp: 29,
+            high_temp_diff: TempDiff::new("-4").unwrap(),
+            low_temp: 26,
+            low_temp_diff: TempDiff::new("+1").unwrap(),
+            rain_probability: RainProbability::default(),
+            wind: None,
+            wave: None,
+        }
+    }
+
+    #[test]
+    fn test_jst_hour() {
+        // 18:00 in JST on a UTC host
+        let hour = jst_hour(Utc.with_ymd_and_hms(2021, 7, 30, 9, 0, 0).unwrap());
+        let rain_probability = RainProbability::new([Some(0), Some(10), Some(20), Some(60)]);
+
+        assert_eq!(hour, 18);
+        assert_eq!(
+            Day::Auto { evening_hour: 18 }.resolve(hour),
+            ResolvedDay::Tomorrow
+        );
+        assert_eq!(rain_probability.at(hour), Some(60));
+    }
+
+    #[test]
+    fn test_is_alerting() {
+        let alert_only = AlertOnly {
+            min_level: Severity::Warning,
+            action: NoAlertAction::Clear,
+        };
+
+        assert!(!alert_only.is_alerting(&forecast(vec![])));
+        assert!(!alert_only.is_alerting(&forecast(vec![Advisory::new("雷", Severity::Alert)])));
+        assert!(alert_only.is_alerting(&forecast(vec![Advisory::new("大雨", Severity::Warning)])));
+        assert!(alert_only.is_alerting(&forecast(vec![Advisory::new(
+            "大雨",
+            Severity::SpecialWarning
+        )])));
+    }
+
+    mod test_build_status {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        fn build(
+            advisories: Vec<Advisory>,
+            alert_only: Option<AlertOnly>,
+        ) -> Option<(String, String)> {
+            let user = User {
+                name: "alice".to_string(),
+                url: "https://tenki.jp/forecast/3/16/4410/13120".to_string(),
+                token: None,
+                template: "{place}: {weather}".parse().unwrap(),
+            };
+            Runner::build_status(
+                &user,
+                &forecast(advisories),
+                &TextOptions::default(),
+                alert_only,
+                None,
+            )
+            .unwrap()
+        }
+
+        fn alert_only(action: NoAlertAction) -> Option<AlertOnly> {
+            Some(AlertOnly {
+                min_level: Severity::Warning,
+                action,
+            })
+        }
+
+        #[test]
+        fn not_alert_only() {
+            assert_eq!(
+                build(vec![], None),
+                Some((":sunny:".to_string(), "練馬区: 晴".to_string()))
+            );
+        }
+
+        #[test]
+        fn alerting() {
+            let advisories = vec![Advisory::new("大雨", Severity::Warning)];
+
+            assert_eq!(
+                build(advisories, alert_only(NoAlertAction::Keep)),
+                Some((":bucket:".to_string(), "練馬区: 晴".to_string()))
+            );
+        }
+
+        #[test]
+        fn clear() {
+            let advisories = vec![Advisory::new("雷", Severity::Alert)];
+
+            assert_eq!(
+                build(advisories, alert_only(NoAlertAction::Clear)),
+                Some(("".to_string(), "".to_string()))
+            );
+        }
+
+        #[test]
+        fn keep() {
+            let advisories = vec![Advisory::new("雷", Severity::Alert)];
+
+            assert_eq!(build(advisories, alert_only(NoAlertAction::Keep)), None);
+        }
+    }
+
+    #[test]
+    fn test_updated() {
+        let now = Utc::now().timestamp();
+        let profile = Profile::new(":sunny:", "晴", Some(Duration::from_secs(7200)));
+        let Outcome::Updated {
+            emoji,
+            text,
+            expiration,
+        } = profile.into()
+        else {
+            panic!("not updated");
+        };
+        let mut state = State::default();
+        state.update("alice", &emoji, &text, now, expiration);
+
+        assert!(state.is_unchanged("alice", ":sunny:", "晴", now + 60));
+        // Sent again before Slack clears it.
+        assert!(!state.is_unchanged("alice", ":sunny:", "晴", now + 3601));
+    }
+
+    #[test]
+    fn test_summary() {
+        let summary = Summary {
+            date_times: vec![],
+            unknown_emoji: UnknownEmoji::default(),
+            failed_urls: vec![(
+                "https://tenki.jp/forecast/3/16/4410/13120".to_string(),
+                Error::HttpStatus(StatusCode::NOT_FOUND),
+            )],
+            notifications: vec![Notification {
+                place: "練馬区".to_string(),
+                result: Ok(AdvisoryChanges {
+                    issued: vec![Advisory::new("大雨", Severity::Warning)],
+                    ..AdvisoryChanges::default()
+                }),
+            }],
+            posts: vec![("練馬区".to_string(), Ok(()))],
+            reports: vec![
+                Report {
+                    name: "alice".to_string(),
+                    result: Ok(Outcome::Updated {
+                        emoji: ":sunny:".to_string(),
+                        text: "晴".to_string(),
+                        expiration: 0,
+                    }),
+                },
+                Report {
+                    name: "bob".to_string(),
+                    result: Ok(Outcome::Skipped),
+                },
+                Report {
+                    name: "carol".to_string(),
+                    result: Err(Error::InvalidConfig(
+                        "token of carol is required".to_string(),
+                    )),
+                },
+                Report {
+                    name: "dave".to_string(),
+                    result: Ok(Outcome::NoAlert),
+                },
+                Report {
+                    name: "erin".to_string(),
+                    result: Ok(Outcome::Updated {
+                        emoji: "".to_string(),
+                        text: "".to_string(),
+                        expiration: 0,
+                    }),
+                },
+            ],
+        };
+
+        assert!(!summary.is_success());
+        assert_eq!(
+            summary.to_string(),
+            "https://tenki.jp/forecast/3/16/4410/13120: failed: unexpected HTTP status: 404 Not Found
+練馬区: posted 1 issued, 0 lifted, 0 upgraded and 0 downgraded advisories
+練馬区: posted the forecast
+alice: updated \":sunny:\", \"晴\"
+bob: skipped
+carol: failed: invalid config: token of carol is required
+dave: no alert, kept the status
+erin: cleared
+"
+        );
+    }
+}
@@ -0,0 +1,184 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, FixedOffset, NaiveTime, TimeZone, Timelike, Utc};
+use rand::Rng;
+
+const JST_OFFSET_SECS: i32 = 9 * 3600;
+/// tenki.jp pages are updated a few minutes after the 発表 time.
+const PUBLICATION_DELAY: Duration = Duration::from_secs(5 * 60);
+
+/// The longest interval, so that the next run doesn't overflow a time.
+pub const MAX_INTERVAL: Duration = Duration::from_secs(366 * 24 * 3600);
+
+/// When the daemon mode runs next.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schedule {
+    pub interval: Duration,
+    pub jitter: Duration, // the max random delay added to each run
+}
+
+pub fn jst() -> FixedOffset {
+    FixedOffset::east_opt(JST_OFFSET_SECS).unwrap()
+}
+
+/// The hour in JST, which the 発表 times and the 降水確率 slots of tenki.jp are in, whatever the time zone of the host.
+pub fn jst_hour(now: DateTime<Utc>) -> u32 {
+    now.with_timezone(&jst()).hour()
+}
+
+impl Schedule {
+    /// The time of the next run without jitter. The runs are aligned to the 発表 time, so that one
+    /// lands shortly after the next publication, e.g. 16:05, 16:15, ... for 16:00 and a 10 minutes interval.
+    pub fn next_run(
+        &self,
+        now: DateTime<FixedOffset>,
+        published_at: Option<DateTime<FixedOffset>>,
+    ) -> DateTime<FixedOffset> {
+        let interval =
+            chrono::Duration::from_std(self.interval.clamp(Duration::from_secs(1), MAX_INTERVAL))
+                .unwrap();
+        let Some(published_at) = published_at else {
+            return now + interval;
+        };
+
+        let anchor = published_at + chrono::Duration::from_std(PUBLICATION_DELAY).unwrap();
+        if anchor > now {
+            return anchor.min(now + interval);
+        }
+
+        let interval_secs = interval.num_seconds().max(1);
+        let elapsed = (now - anchor).num_seconds();
+        anchor + chrono::Duration::seconds((elapsed / interval_secs + 1) * interval_secs)
+    }
+
+    /// The time to wait until the next run, including a random jitter.
+    pub fn delay(
+        &self,
+        now: DateTime<FixedOffset>,
+        published_at: Option<DateTime<FixedOffset>>,
+    ) -> Duration {
+        let delay = (self.next_run(now, published_at) - now)
+            .to_std()
+            .unwrap_or_default();
+        let jitter = match self.jitter.as_millis() as u64 {
+            0 => 0,
+            x => rand::thread_rng().gen_range(0..=x),
+        };
+        delay + Duration::from_millis(jitter)
+    }
+}
+
+/// Parses a 発表 time like `30日16:00`, which is the latest such time not after `now`.
+pub fn parse_published_at(
+    date_time: &str,
+    now: DateTime<FixedOffset>,
+) -> Option<DateTime<FixedOffset>> {
+    let (day, time) = date_time.split_once('日')?;
+    let day = day.trim().parse::<u32>().ok()?;
+    let time = NaiveTime::parse_from_str(time.trim(), "%H:%M").ok()?;
+
+    // The day may belong to the last month, e.g. 31日 on the 1st.
+    let this_month = now.date_naive().with_day(1)?;
+    let last_month = this_month.pred_opt()?.with_day(1)?;
+    [this_month, last_month]
+        .iter()
+        .filter_map(|x| x.with_day(day))
+        .filter_map(|x| {
+            now.timezone()
+                .from_local_datetime(&x.and_time(time))
+                .single()
+        })
+        .find(|x| *x <= now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<FixedOffset> {
+        jst()
+            .with_ymd_and_hms(2021, 8, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    mod test_next_run {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        fn schedule(minutes: u64) -> Schedule {
+            Schedule {
+                interval: Duration::from_secs(minutes * 60),
+                jitter: Duration::from_secs(0),
+            }
+        }
+
+        #[test]
+        fn unknown_published_at() {
+            assert_eq!(schedule(10).next_run(at(14, 3, 47), None), at(14, 3, 57));
+        }
+
+        #[test]
+        fn out_of_range_interval() {
+            let now = at(14, 3, 47);
+
+            assert!(schedule(0).next_run(now, None) > now);
+            assert_eq!(
+                schedule(u64::MAX / 60).next_run(now, None),
+                now + chrono::Duration::from_std(MAX_INTERVAL).unwrap()
+            );
+        }
+
+        #[test]
+        fn aligned_to_published_at() {
+            let published_at = Some(at(14, 2, 0));
+
+            assert_eq!(
+                schedule(10).next_run(at(14, 3, 47), published_at),
+                at(14, 3, 55)
+            );
+            assert_eq!(
+                schedule(10).next_run(at(14, 3, 55), published_at),
+                at(14, 4, 5)
+            );
+            assert_eq!(
+                schedule(180).next_run(at(14, 3, 47), published_at),
+                at(14, 5, 5)
+            );
+        }
+
+        #[test]
+        fn just_published() {
+            let published_at = Some(at(14, 2, 0));
+
+            assert_eq!(
+                schedule(10).next_run(at(14, 2, 1), published_at),
+                at(14, 2, 5)
+            );
+        }
+    }
+
+    #[test]
+    fn test_delay() {
+        let schedule = Schedule {
+            interval: Duration::from_secs(600),
+            jitter: Duration::from_secs(30),
+        };
+        let delay = schedule.delay(at(14, 3, 47), None);
+
+        assert!((Duration::from_secs(600)..=Duration::from_secs(630)).contains(&delay));
+    }
+
+    #[test]
+    fn test_parse_published_at() {
+        assert_eq!(
+            parse_published_at("14日02:00", at(14, 3, 47)),
+            Some(at(14, 2, 0))
+        );
+        assert_eq!(
+            parse_published_at("31日17:00", at(1, 3, 0)),
+            Some(jst().with_ymd_and_hms(2021, 7, 31, 17, 0, 0).unwrap())
+        );
+        assert_eq!(parse_published_at("unknown", at(14, 3, 47)), None);
+    }
+}
@@ -1,42 +1,472 @@
-use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::time::Duration;
 
-use reqwest::{header, Client, StatusCode};
+use chrono::Utc;
+use reqwest::{Client, Response, StatusCode, header};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
 
+use crate::emoji::EmojiMaps;
+use crate::error::{Error, Result};
+use crate::retry::RetryPolicy;
+
+const GET_USERS_PROFILE_API: &str = "https://slack.com/api/users.profile.get";
 const SET_USERS_PROFILE_API: &str = "https://slack.com/api/users.profile.set";
+const POST_MESSAGE_API: &str = "https://slack.com/api/chat.postMessage";
+/// Slack doesn't accept a longer status text.
+pub const STATUS_TEXT_MAX_LENGTH: usize = 100;
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub status_text: String,
+    pub status_emoji: String,
+    pub status_expiration: i64, // Unix time. 0 means that the status never expires.
+}
+
+impl Profile {
+    pub fn new(emoji: &str, text: &str, expire_after: Option<Duration>) -> Self {
+        Self {
+            status_text: text.to_string(),
+            status_emoji: emoji.to_string(),
+            status_expiration: expire_after
+                .map(|x| Utc::now().timestamp() + x.as_secs() as i64)
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.status_emoji.is_empty() && self.status_text.is_empty()
+    }
+
+    /// Whether the status emoji is one that this tool sets.
+    pub fn has_known_emoji(&self, emoji_maps: &EmojiMaps) -> bool {
+        emoji_maps.contains(&self.status_emoji)
+    }
+
+    /// Whether the status is empty or was set by this tool, not by hand. `last_emoji` is the one which
+    /// this tool sent last time, which may not be in the maps any more.
+    pub fn is_overwritable(&self, emoji_maps: &EmojiMaps, last_emoji: Option<&str>) -> bool {
+        self.is_empty()
+            || self.has_known_emoji(emoji_maps)
+            || last_emoji.is_some_and(|x| !x.is_empty() && x == self.status_emoji)
+    }
+}
+
+/// A Block Kit message. `text` is shown in the notifications and by the clients which can't show the blocks.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Message {
+    pub text: String,
+    pub blocks: Vec<Value>,
+}
+
+/// Where a message is posted: a channel with chat.postMessage, or an incoming webhook.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Destination {
+    Channel { token: String, channel: String },
+    Webhook(String),
+}
+
+impl Destination {
+    pub async fn post(&self, message: &Message, retry_policy: &RetryPolicy) -> Result<()> {
+        match self {
+            Self::Channel { token, channel } => {
+                SlackRequest::with_retry(token, retry_policy)
+                    .post_message(channel, message)
+                    .await?;
+                Ok(())
+            }
+            Self::Webhook(url) => Webhook::with_retry(url, retry_policy).post(message).await,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SlackResponse {
+    pub ok: bool,
+    pub error: Option<String>,
+    pub needed: Option<String>, // the scope which is missing
+    pub warning: Option<String>,
+    pub profile: Option<Profile>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SlackError {
+    InvalidAuth,
+    NotAuthed,
+    MissingScope(Option<String>),
+    AccountInactive,
+    TokenRevoked,
+    InvalidEmoji,
+    RateLimited(Option<Duration>), // Retry-After
+    Other(String),
+}
+
+impl SlackError {
+    fn from_response(response: SlackResponse) -> Self {
+        match response.error.as_deref() {
+            Some("invalid_auth") => Self::InvalidAuth,
+            Some("not_authed") => Self::NotAuthed,
+            Some("missing_scope") => Self::MissingScope(response.needed),
+            Some("account_inactive") => Self::AccountInactive,
+            Some("token_revoked") => Self::TokenRevoked,
+            Some("profile_status_set_failed_not_valid_emoji") => Self::InvalidEmoji,
+            Some("ratelimited") => Self::RateLimited(None),
+            Some(x) => Self::Other(x.to_string()),
+            None => Self::Other("unknown_error".to_string()),
+        }
+    }
+}
+
+impl Display for SlackError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidAuth => write!(f, "invalid_auth: the token is invalid"),
+            Self::NotAuthed => write!(f, "not_authed: no token was provided"),
+            Self::MissingScope(Some(needed)) => write!(f, "missing_scope: {needed} is needed"),
+            Self::MissingScope(None) => write!(f, "missing_scope"),
+            Self::AccountInactive => write!(f, "account_inactive"),
+            Self::TokenRevoked => write!(f, "token_revoked"),
+            Self::InvalidEmoji => write!(f, "the status emoji doesn't exist in the workspace"),
+            Self::RateLimited(Some(retry_after)) => {
+                write!(f, "ratelimited: retry after {retry_after:?}")
+            }
+            Self::RateLimited(None) => write!(f, "ratelimited"),
+            Self::Other(x) => write!(f, "{x}"),
+        }
+    }
+}
 
 pub struct SlackRequest {
     token: String,
+    retry_policy: RetryPolicy,
 }
 
 impl SlackRequest {
     pub fn new(token: &str) -> Self {
+        Self::with_retry(token, &RetryPolicy::default())
+    }
+
+    /// Retries on 429 after Retry-After, and on 5xx and timeouts with exponential backoff.
+    pub fn with_retry(token: &str, retry_policy: &RetryPolicy) -> Self {
         Self {
             token: format!("Bearer {token}"),
+            retry_policy: retry_policy.clone(),
         }
     }
 
-    pub async fn update_status(
-        &self,
-        emoji: &str,
-        text: &str,
-    ) -> Result<(StatusCode, String), Box<dyn std::error::Error>> {
-        let mut profile = HashMap::new();
-        profile.insert("status_emoji", emoji);
-        profile.insert("status_text", text);
-        let mut map = HashMap::new();
-        map.insert("profile", profile);
-
-        let client = Client::builder().build()?;
-        let res = client
-            .post(SET_USERS_PROFILE_API)
-            .header(header::AUTHORIZATION, &self.token)
-            .header(header::CONTENT_TYPE, "application/json; charset=utf-8")
-            .json(&map)
-            .send()
+    /// The status is cleared by Slack at `status_expiration`, or never when it's 0.
+    pub async fn update_status(&self, profile: &Profile) -> Result<SlackResponse> {
+        let map = json!({ "profile": profile });
+
+        let client = self.client()?;
+        self.retry_policy
+            .run(|| async {
+                let res = client
+                    .post(SET_USERS_PROFILE_API)
+                    .header(header::AUTHORIZATION, &self.token)
+                    .header(header::CONTENT_TYPE, "application/json; charset=utf-8")
+                    .json(&map)
+                    .send()
+                    .await?;
+                Self::parse_response(res).await
+            })
+            .await
+    }
+
+    /// It needs chat:write scope, and the user needs to be in the channel.
+    pub async fn post_message(&self, channel: &str, message: &Message) -> Result<SlackResponse> {
+        let mut map = json!(message);
+        map["channel"] = json!(channel);
+
+        let client = self.client()?;
+        self.retry_policy
+            .run(|| async {
+                let res = client
+                    .post(POST_MESSAGE_API)
+                    .header(header::AUTHORIZATION, &self.token)
+                    .header(header::CONTENT_TYPE, "application/json; charset=utf-8")
+                    .json(&map)
+                    .send()
+                    .await?;
+                Self::parse_response(res).await
+            })
+            .await
+    }
+
+    pub async fn get_profile(&self) -> Result<Profile> {
+        let client = self.client()?;
+        let response = self
+            .retry_policy
+            .run(|| async {
+                let res = client
+                    .get(GET_USERS_PROFILE_API)
+                    .header(header::AUTHORIZATION, &self.token)
+                    .send()
+                    .await?;
+                Self::parse_response(res).await
+            })
             .await?;
+        Ok(response.profile.unwrap_or_default())
+    }
+
+    fn client(&self) -> Result<Client> {
+        Ok(Client::builder()
+            .timeout(self.retry_policy.timeout)
+            .build()?)
+    }
 
-        let status_code = res.status();
+    async fn parse_response(res: Response) -> Result<SlackResponse> {
+        let status = res.status();
+        let retry_after = res
+            .headers()
+            .get(header::RETRY_AFTER)
+            .and_then(|x| x.to_str().ok())
+            .map(|x| x.to_string());
         let body = res.text().await?;
-        Ok((status_code, body))
+        Self::check_response(status, retry_after.as_deref(), &body)
+    }
+
+    fn check_response(
+        status: StatusCode,
+        retry_after: Option<&str>,
+        body: &str,
+    ) -> Result<SlackResponse> {
+        check_status(status, retry_after)?;
+
+        let response = serde_json::from_str::<SlackResponse>(body)
+            .map_err(|_| Error::Slack(SlackError::Other(format!("invalid response: {body}"))))?;
+        match response.ok {
+            true => Ok(response),
+            false => Err(Error::Slack(SlackError::from_response(response))),
+        }
+    }
+}
+
+/// An incoming webhook, which posts to the channel it was created for.
+pub struct Webhook {
+    url: String,
+    retry_policy: RetryPolicy,
+}
+
+impl Webhook {
+    pub fn with_retry(url: &str, retry_policy: &RetryPolicy) -> Self {
+        Self {
+            url: url.to_string(),
+            retry_policy: retry_policy.clone(),
+        }
+    }
+
+    pub async fn post(&self, message: &Message) -> Result<()> {
+        let client = Client::builder()
+            .timeout(self.retry_policy.timeout)
+            .build()?;
+        self.retry_policy
+            .run(|| async {
+                let res = client.post(&self.url).json(message).send().await?;
+                let status = res.status();
+                let retry_after = res
+                    .headers()
+                    .get(header::RETRY_AFTER)
+                    .and_then(|x| x.to_str().ok())
+                    .map(|x| x.to_string());
+                let body = res.text().await?;
+                Self::check_response(status, retry_after.as_deref(), &body)
+            })
+            .await
+    }
+
+    /// A webhook responds with a plain text: `ok`, or an error like `invalid_payload` with 4xx.
+    fn check_response(status: StatusCode, retry_after: Option<&str>, body: &str) -> Result<()> {
+        match status {
+            x if x.is_client_error() && x != StatusCode::TOO_MANY_REQUESTS => {
+                Err(Error::Slack(SlackError::Other(body.trim().to_string())))
+            }
+            x => check_status(x, retry_after),
+        }
+    }
+}
+
+/// 429 and the other non-2xx statuses are errors.
+fn check_status(status: StatusCode, retry_after: Option<&str>) -> Result<()> {
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        let retry_after = retry_after
+            .and_then(|x| x.trim().parse::<u64>().ok())
+            .map(Duration::from_secs);
+        return Err(Error::Slack(SlackError::RateLimited(retry_after)));
+    }
+
+    if !status.is_success() {
+        return Err(Error::HttpStatus(status));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod test_profile {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn never_expires() {
+            let profile = Profile::new(":sunny:", "晴", None);
+
+            assert_eq!(
+                serde_json::to_value(&profile).unwrap(),
+                json!({ "status_text": "晴", "status_emoji": ":sunny:", "status_expiration": 0 })
+            );
+        }
+
+        #[test]
+        fn expires() {
+            let now = Utc::now().timestamp();
+            let profile = Profile::new(":sunny:", "晴", Some(Duration::from_secs(7200)));
+
+            assert!((now + 7200..=now + 7201).contains(&profile.status_expiration));
+        }
+
+        #[test]
+        fn overwritable() {
+            let emoji_maps = EmojiMaps::default();
+
+            assert!(Profile::default().is_overwritable(&emoji_maps, None));
+            assert!(Profile::new(":sunny:", "場所: 晴", None).is_overwritable(&emoji_maps, None));
+            assert!(
+                Profile::new(":cyclone:", "場所: 暴風警報", None)
+                    .is_overwritable(&emoji_maps, None)
+            );
+            assert!(
+                !Profile::new(":palm_tree:", "On vacation", None)
+                    .is_overwritable(&emoji_maps, None)
+            );
+            assert!(!Profile::new("", "On vacation", None).is_overwritable(&emoji_maps, None));
+            assert!(!Profile::new("", "On vacation", None).is_overwritable(&emoji_maps, Some("")));
+        }
+
+        #[test]
+        fn last_emoji() {
+            // An emoji of an override file which was removed since
+            let emoji_maps = EmojiMaps::default();
+            let profile = Profile::new(":tenki_hare:", "場所: 晴", None);
+
+            assert!(profile.is_overwritable(&emoji_maps, Some(":tenki_hare:")));
+            assert!(!profile.is_overwritable(&emoji_maps, Some(":sunny:")));
+            assert!(!profile.is_overwritable(&emoji_maps, None));
+        }
+    }
+
+    mod test_check_response {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn ok() {
+            let response =
+                SlackRequest::check_response(StatusCode::OK, None, r#"{"ok":true,"profile":{}}"#)
+                    .unwrap();
+
+            assert!(response.ok);
+        }
+
+        #[test]
+        fn profile() {
+            let response = SlackRequest::check_response(
+                StatusCode::OK,
+                None,
+                r#"{"ok":true,"profile":{"status_text":"On vacation","status_emoji":":palm_tree:","status_expiration":0,"real_name":"name"}}"#,
+            )
+            .unwrap();
+
+            assert_eq!(
+                response.profile,
+                Some(Profile::new(":palm_tree:", "On vacation", None))
+            );
+        }
+
+        #[test]
+        fn invalid_auth() {
+            let result = SlackRequest::check_response(
+                StatusCode::OK,
+                None,
+                r#"{"ok":false,"error":"invalid_auth"}"#,
+            );
+
+            assert!(matches!(result, Err(Error::Slack(SlackError::InvalidAuth))));
+        }
+
+        #[test]
+        fn missing_scope() {
+            let result = SlackRequest::check_response(
+                StatusCode::OK,
+                None,
+                r#"{"ok":false,"error":"missing_scope","needed":"users.profile:write","provided":"identify"}"#,
+            );
+
+            match result {
+                Err(Error::Slack(e)) => assert_eq!(
+                    e,
+                    SlackError::MissingScope(Some("users.profile:write".to_string()))
+                ),
+                _ => panic!("unexpected result: {result:?}"),
+            }
+        }
+
+        #[test]
+        fn ratelimited() {
+            let result = SlackRequest::check_response(
+                StatusCode::TOO_MANY_REQUESTS,
+                Some("30"),
+                r#"{"ok":false,"error":"ratelimited"}"#,
+            );
+
+            match result {
+                Err(e) => {
+                    assert!(e.is_retryable());
+                    assert_eq!(e.retry_after(), Some(Duration::from_secs(30)));
+                }
+                _ => panic!("unexpected result: {result:?}"),
+            }
+        }
+
+        #[test]
+        fn server_error() {
+            let result = SlackRequest::check_response(StatusCode::SERVICE_UNAVAILABLE, None, "");
+
+            assert!(matches!(
+                result,
+                Err(Error::HttpStatus(StatusCode::SERVICE_UNAVAILABLE))
+            ));
+        }
+    }
+
+    mod test_webhook_response {
+        use super::*;
+
+        #[test]
+        fn ok() {
+            assert!(Webhook::check_response(StatusCode::OK, None, "ok").is_ok());
+        }
+
+        #[test]
+        fn invalid_payload() {
+            let result = Webhook::check_response(StatusCode::BAD_REQUEST, None, "invalid_payload");
+
+            assert!(
+                matches!(result, Err(Error::Slack(SlackError::Other(x))) if x == "invalid_payload")
+            );
+        }
+
+        #[test]
+        fn ratelimited() {
+            let result = Webhook::check_response(StatusCode::TOO_MANY_REQUESTS, Some("5"), "");
+
+            assert!(matches!(
+                result,
+                Err(Error::Slack(SlackError::RateLimited(Some(_))))
+            ));
+        }
     }
 }
@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, FixedOffset, Timelike};
+use serde::{Deserialize, Serialize};
+
+use crate::config::xdg_path;
+use crate::emoji::UnknownEmoji;
+use crate::error::{Error, Result};
+use crate::models::Advisory;
+
+pub const DEFAULT_KEY: &str = "default";
+const STATE_FILE: &str = "rusty-slack-weather-status/state.toml";
+
+/// The statuses which were sent last time, keyed by a user, and the keys which didn't have emoji so far.
+/// `advisories` are the ones which were notified last time, and `cards` are the dates in JST of the forecast cards
+/// which were posted last time, both keyed by a forecast URL.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct State {
+    #[serde(default)]
+    pub statuses: HashMap<String, SentStatus>,
+    #[serde(default)]
+    pub unknown_emoji: UnknownEmoji,
+    #[serde(default)]
+    pub advisories: HashMap<String, Vec<Advisory>>,
+    #[serde(default)]
+    pub cards: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SentStatus {
+    pub emoji: String,
+    pub text: String,
+    pub sent_at: i64,    // Unix time
+    pub expiration: i64, // Unix time. 0 means that the status never expires.
+}
+
+impl SentStatus {
+    /// Whether more than half of the lifetime has passed, so the status should be sent again.
+    fn is_expiring(&self, now: i64) -> bool {
+        self.expiration != 0 && now >= self.sent_at + (self.expiration - self.sent_at) / 2
+    }
+}
+
+impl State {
+    /// `$XDG_STATE_HOME/rusty-slack-weather-status/state.toml` or `~/.local/state/rusty-slack-weather-status/state.toml`
+    pub fn default_path() -> Option<PathBuf> {
+        xdg_path("XDG_STATE_HOME", ".local/state", STATE_FILE)
+    }
+
+    /// Returns an empty state when the file doesn't exist yet.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let raw = fs::read_to_string(path)?;
+        toml::from_str(&raw).map_err(|e| Error::InvalidFile(path.to_path_buf(), e.to_string()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let raw = toml::to_string(self)
+            .map_err(|e| Error::InvalidFile(path.to_path_buf(), e.to_string()))?;
+        Ok(fs::write(path, raw)?)
+    }
+
+    /// Whether the same status was sent and it doesn't need to be refreshed before its expiration.
+    pub fn is_unchanged(&self, key: &str, emoji: &str, text: &str, now: i64) -> bool {
+        self.statuses
+            .get(key)
+            .is_some_and(|x| x.emoji == emoji && x.text == text && !x.is_expiring(now))
+    }
+
+    /// Whether the card of the date, e.g. 2021-07-30, was posted already.
+    pub fn is_card_posted(&self, url: &str, date: &str) -> bool {
+        self.cards.get(url).is_some_and(|x| x == date)
+    }
+
+    /// Whether the card should be posted now, i.e. from `post_hour` and once a day, even though tenki.jp publishes
+    /// the page a few times a day. `now` is in JST.
+    pub fn is_card_due(&self, url: &str, now: DateTime<FixedOffset>, post_hour: u32) -> bool {
+        now.hour() >= post_hour && !self.is_card_posted(url, &now.date_naive().to_string())
+    }
+
+    pub fn update(&mut self, key: &str, emoji: &str, text: &str, now: i64, expiration: i64) {
+        self.statuses.insert(
+            key.to_string(),
+            SentStatus {
+                emoji: emoji.to_string(),
+                text: text.to_string(),
+                sent_at: now,
+                expiration,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Severity;
+    use crate::schedule::jst;
+    use chrono::TimeZone;
+    use pretty_assertions::assert_eq;
+
+    mod test_is_unchanged {
+        use super::*;
+
+        #[test]
+        fn never_sent() {
+            let state = State::default();
+
+            assert!(!state.is_unchanged(DEFAULT_KEY, ":sunny:", "晴", 0));
+        }
+
+        #[test]
+        fn same_status() {
+            let mut state = State::default();
+            state.update(DEFAULT_KEY, ":sunny:", "晴", 1000, 0);
+
+            assert!(state.is_unchanged(DEFAULT_KEY, ":sunny:", "晴", 2000));
+            assert!(!state.is_unchanged("other", ":sunny:", "晴", 2000));
+        }
+
+        #[test]
+        fn changed_status() {
+            let mut state = State::default();
+            state.update(DEFAULT_KEY, ":sunny:", "晴", 1000, 0);
+
+            assert!(!state.is_unchanged(DEFAULT_KEY, ":umbrella:", "雨", 2000));
+            assert!(!state.is_unchanged(DEFAULT_KEY, ":sunny:", "晴 最高: 30℃", 2000));
+        }
+
+        #[test]
+        fn expiring_status() {
+            let mut state = State::default();
+            state.update(DEFAULT_KEY, ":sunny:", "晴", 1000, 8200);
+
+            assert!(state.is_unchanged(DEFAULT_KEY, ":sunny:", "晴", 4599));
+            assert!(!state.is_unchanged(DEFAULT_KEY, ":sunny:", "晴", 4600));
+        }
+    }
+
+    #[test]
+    fn test_is_card_posted() {
+        let url = "https://tenki.jp/forecast/3/16/4410/13120";
+        let mut state = State::default();
+        assert!(!state.is_card_posted(url, "2021-07-30"));
+
+        // Once a day, even if the page is published again.
+        state
+            .cards
+            .insert(url.to_string(), "2021-07-30".to_string());
+        assert!(state.is_card_posted(url, "2021-07-30"));
+        assert!(!state.is_card_posted(url, "2021-07-31"));
+        assert!(!state.is_card_posted("https://tenki.jp/forecast/3/16/4410/13113", "2021-07-30"));
+
+        let at = |day, hour| jst().with_ymd_and_hms(2021, 7, day, hour, 0, 0).unwrap();
+        assert!(!state.is_card_due(url, at(30, 17), 7));
+        assert!(!state.is_card_due(url, at(31, 6), 7));
+        assert!(state.is_card_due(url, at(31, 7), 7));
+        assert!(state.is_card_due(url, at(31, 0), 0));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir()
+            .join(format!("rusty-slack-weather-status-{}", std::process::id()))
+            .join("state.toml");
+        assert_eq!(State::load(&path).unwrap(), State::default());
+
+        let mut state = State::default();
+        state.update(DEFAULT_KEY, ":sunny:", "晴", 1000, 8200);
+        state.unknown_emoji.weather.insert("31".to_string());
+        state.advisories.insert(
+            "https://tenki.jp/forecast/3/16/4410/13120".to_string(),
+            vec![
+                Advisory::new("大雨", Severity::Warning),
+                Advisory::new("新しい", Severity::Alert),
+            ],
+        );
+        state.cards.insert(
+            "https://tenki.jp/forecast/3/16/4410/13120".to_string(),
+            "2021-07-30".to_string(),
+        );
+        state.save(&path).unwrap();
+
+        assert_eq!(State::load(&path).unwrap(), state);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,247 @@
+use std::str::FromStr;
+
+use crate::error::{Error, Result};
+
+/// The format of `Forecast::build_text`.
+pub const DEFAULT_TEMPLATE: &str = "{place}: {?advisories}{advisories} {weather_emoji}: {/advisories}{weather} 最高: {high_temp}{temp_unit}{?high_temp_diff}[{high_temp_diff}]{/high_temp_diff} 最低: {low_temp}{temp_unit}{?low_temp_diff}[{low_temp_diff}]{/low_temp_diff}{?precip} ☔{precip}%{/precip}{?date_time} 発表: {date_time}{/date_time}";
+
+/// A value of a forecast which can be put in a template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Field {
+    Place,
+    Weather,
+    WeatherEmoji,
+    HighTemp,
+    HighTempDiff,
+    LowTemp,
+    LowTempDiff,
+    TempUnit, // ℃ or ℉
+    DateTime,
+    SpecialWarnings, // 特別警報
+    Warnings,        // 警報
+    Alerts,          // 注意報
+    Advisories,      // all of the above
+    Precip,          // 降水確率. Empty without `--rain`.
+}
+
+const FIELDS: [(&str, Field); 14] = [
+    ("place", Field::Place),
+    ("weather", Field::Weather),
+    ("weather_emoji", Field::WeatherEmoji),
+    ("high_temp", Field::HighTemp),
+    ("high_temp_diff", Field::HighTempDiff),
+    ("low_temp", Field::LowTemp),
+    ("low_temp_diff", Field::LowTempDiff),
+    ("temp_unit", Field::TempUnit),
+    ("date_time", Field::DateTime),
+    ("special_warnings", Field::SpecialWarnings),
+    ("warnings", Field::Warnings),
+    ("alerts", Field::Alerts),
+    ("advisories", Field::Advisories),
+    ("precip", Field::Precip),
+];
+
+impl Field {
+    pub fn name(&self) -> &'static str {
+        FIELDS.iter().find(|(_, x)| x == self).unwrap().0
+    }
+}
+
+impl FromStr for Field {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        FIELDS
+            .iter()
+            .find(|(name, _)| *name == s)
+            .map(|(_, x)| *x)
+            .ok_or_else(|| Error::InvalidTemplate(format!("unknown placeholder: {{{s}}}")))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    Placeholder(Field),
+    Section(Field, Vec<Part>), // rendered only when the field isn't empty
+}
+
+/// A status text format. `{field}` is replaced with a value, `{?field}...{/field}` is kept only
+/// when the field isn't empty, and `{{` and `}}` are literal braces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Default for Template {
+    fn default() -> Self {
+        Self::parse(DEFAULT_TEMPLATE).unwrap()
+    }
+}
+
+impl FromStr for Template {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+impl Template {
+    pub fn parse(template: &str) -> Result<Self> {
+        // The sections being parsed, and the parts of each of them.
+        let mut stack: Vec<(Option<Field>, Vec<Part>)> = vec![(None, vec![])];
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '}' => return Err(Error::InvalidTemplate("unmatched }".to_string())),
+                '{' => {
+                    let mut tag = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(x) => tag.push(x),
+                            None => {
+                                return Err(Error::InvalidTemplate(format!("unclosed {{{tag}")));
+                            }
+                        }
+                    }
+
+                    let parts = &mut stack.last_mut().unwrap().1;
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+
+                    if let Some(name) = tag.strip_prefix('?') {
+                        stack.push((Some(name.parse()?), vec![]));
+                    } else if let Some(name) = tag.strip_prefix('/') {
+                        let field = name.parse()?;
+                        match stack.pop() {
+                            Some((Some(x), parts)) if x == field => {
+                                stack.last_mut().unwrap().1.push(Part::Section(x, parts))
+                            }
+                            _ => {
+                                return Err(Error::InvalidTemplate(format!(
+                                    "unexpected {{/{name}}}"
+                                )));
+                            }
+                        }
+                    } else {
+                        parts.push(Part::Placeholder(tag.parse()?));
+                    }
+                }
+                c => text.push(c),
+            }
+        }
+
+        if !text.is_empty() {
+            stack.last_mut().unwrap().1.push(Part::Text(text));
+        }
+        match stack.pop() {
+            Some((None, parts)) if stack.is_empty() => Ok(Self { parts }),
+            Some((Some(field), _)) => Err(Error::InvalidTemplate(format!(
+                "unclosed section: {{?{}}}",
+                field.name()
+            ))),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Fills in the placeholders. `value` is called only for the fields which are rendered.
+    pub fn render<F>(&self, value: F) -> Result<String>
+    where
+        F: Fn(Field) -> Result<String>,
+    {
+        let mut text = String::new();
+        Self::render_parts(&self.parts, &value, &mut text)?;
+        Ok(text)
+    }
+
+    fn render_parts<F>(parts: &[Part], value: &F, text: &mut String) -> Result<()>
+    where
+        F: Fn(Field) -> Result<String>,
+    {
+        for part in parts {
+            match part {
+                Part::Text(x) => text.push_str(x),
+                Part::Placeholder(field) => text.push_str(&value(*field)?),
+                Part::Section(field, parts) => {
+                    if !value(*field)?.is_empty() {
+                        Self::render_parts(parts, value, text)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn value(field: Field) -> Result<String> {
+        match field {
+            Field::Place => Ok("場所".to_string()),
+            Field::Weather => Ok("晴".to_string()),
+            Field::HighTemp => Ok("10".to_string()),
+            _ => Ok("".to_string()),
+        }
+    }
+
+    mod test_parse {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn default_template() {
+            assert!(Template::parse(DEFAULT_TEMPLATE).is_ok());
+        }
+
+        #[test]
+        fn unknown_placeholder() {
+            let result = Template::parse("{place} {temp}");
+
+            assert!(
+                matches!(result, Err(Error::InvalidTemplate(x)) if x == "unknown placeholder: {temp}")
+            );
+        }
+
+        #[test]
+        fn unbalanced() {
+            assert!(Template::parse("{place").is_err());
+            assert!(Template::parse("place}").is_err());
+            assert!(Template::parse("{?alerts}{alerts}").is_err());
+            assert!(Template::parse("{alerts}{/alerts}").is_err());
+            assert!(Template::parse("{?alerts}{/warnings}").is_err());
+        }
+
+        #[test]
+        fn escaped_braces() {
+            let template = Template::parse("{{{place}}}").unwrap();
+
+            assert_eq!(template.render(value).unwrap(), "{場所}");
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let template = Template::parse(
+            "{place}: {weather}{?alerts} {alerts}{/alerts}{?high_temp} {high_temp}℃{/high_temp}",
+        )
+        .unwrap();
+
+        assert_eq!(template.render(value).unwrap(), "場所: 晴 10℃");
+    }
+}